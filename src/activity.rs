@@ -0,0 +1,122 @@
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{error::Error, types::Message};
+
+/// Entries kept in memory; the log file keeps everything.
+const MAX_ENTRIES: usize = 500;
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub time: SystemTime,
+    pub message: Message,
+}
+
+/// Every status message of the session, optionally appended to a file.
+#[derive(Debug, Default)]
+pub struct ActivityLog {
+    entries: VecDeque<LogEntry>,
+    file: Option<File>,
+}
+
+impl ActivityLog {
+    /// Appends to `path` from now on, or stops writing when `None`.
+    pub fn set_file(&mut self, path: Option<&Path>) -> Result<(), Error> {
+        self.file = match path {
+            Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    pub fn push(&mut self, message: Message) {
+        let entry = LogEntry {
+            time: SystemTime::now(),
+            message,
+        };
+        if let Some(ref mut file) = self.file {
+            // A failing log file must not get in the way of the action itself.
+            let _ = file.write_all(entry_line(&entry).as_bytes());
+        }
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn entries(&self) -> &VecDeque<LogEntry> {
+        &self.entries
+    }
+}
+
+pub fn level(message: &Message) -> &'static str {
+    match message {
+        Message::Info(_) => "info",
+        Message::Success(_) => "ok",
+        Message::Error(_) => "error",
+    }
+}
+
+/// One log file line; continuation lines of multi-line errors are indented.
+fn entry_line(entry: &LogEntry) -> String {
+    let text = entry.message.text().trim_end().replace('\n', "\n    ");
+    format!(
+        "{} [{}] {text}\n",
+        format_timestamp(entry.time),
+        level(&entry.message)
+    )
+}
+
+/// Formats `time` as `YYYY-MM-DD HH:MM:SS` in UTC.
+pub fn format_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Civil-from-days, see https://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn formats_utc_timestamps() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        assert_eq!(format_timestamp(at(0)), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(at(951_782_400)), "2000-02-29 00:00:00");
+        assert_eq!(format_timestamp(at(1_792_325_045)), "2026-10-18 12:04:05");
+    }
+
+    #[test]
+    fn writes_multiline_entries() {
+        let entry = LogEntry {
+            time: UNIX_EPOCH,
+            message: Message::Error("wg-quick failed\nline 2\n".into()),
+        };
+        assert_eq!(
+            entry_line(&entry),
+            "1970-01-01 00:00:00 [error] wg-quick failed\n    line 2\n"
+        );
+    }
+}
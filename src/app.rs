@@ -1,11 +1,23 @@
-use std::{fs, time::Duration};
+use std::{
+    collections::HashSet,
+    fs,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
 
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::{
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
+    terminal,
+};
 use qrcode::QrCode;
 use ratatui::{
-    Frame,
+    DefaultTerminal, Frame,
     layout::{Constraint, Layout, Rect},
-    style::{Color, Modifier, Style, Stylize},
+    style::Stylize,
     text::{Line, Text},
     widgets::{List, ListItem, ListState, Paragraph, Wrap},
 };
@@ -14,38 +26,149 @@ use tui_input::{Input, InputRequest};
 use crate::error::Error;
 
 use crate::{
-    types::{EditTunnelDraft, Message, NewServerDraft, NewTunnelDraft, Tunnel},
+    activity::ActivityLog,
+    cidr::split_tunnel,
+    config::WgConfig,
+    diagnostics::{diagnose, probe_mtu},
+    editor::{TextArea, edit_externally},
+    filter::{ListRow, TunnelFilter, bulk_targets, group_rows, marked_names},
+    keymap::{Action, Keymap},
+    lint::{Severity, lint_config},
+    privileged,
+    settings::{SETTINGS, set_settings, settings},
+    state::State,
+    task::{CancelFlag, Task},
+    theme::{Theme, set_theme, theme},
+    types::{
+        BulkResult, CloneTunnelDraft, DiagnosticCheck, EDIT_INTERFACE_KEYS, EDIT_PEER_KEYS,
+        EditTunnelDraft, Message, MtuProbe, NewServerDraft, NewTunnelDraft, PeerDraft,
+        RouteConflict, Tunnel,
+    },
     ui::{
-        bordered_block, label, peer_lines, render_add_menu, render_confirm, render_edit_form,
-        render_full_tunnel_warning, render_help, render_input, render_peer_config, render_peer_qr,
-        section, truncate_key,
+        bordered_block, edit_form_field_at, label, lint_line, peer_lines, render_activity_log,
+        render_add_menu, render_bulk_results, render_confirm, render_diagnostics, render_edit_form,
+        render_help, render_input, render_mtu_probe, render_peer_config, render_peer_qr,
+        render_raw_editor, render_settings, render_up_warning, section, truncate_key,
     },
     wireguard::{
-        add_server_peer, create_server_tunnel, create_tunnel, default_egress_interface,
-        delete_tunnel, detect_public_ip, discover_tunnels, expand_path, export_tunnels_to_zip,
-        generate_private_key, get_interface_info, import_tunnel, is_full_tunnel_config,
-        is_interface_active, parse_tunnel_config, suggest_server_address, update_tunnel_config, wg_quick,
+        add_server_peer, clone_tunnel, create_server_tunnel, create_tunnel,
+        default_egress_interface, delete_tunnel, detect_public_ip, expand_path,
+        export_tunnels_to_zip, find_route_conflicts, generate_private_key, has_listen_port,
+        import_tunnel, is_full_tunnel_config, load_tunnels, parse_tunnel_config,
+        read_tunnel_config, reload_tunnel, rename_tunnel, save_tunnel_config, set_tunnel_tags,
+        suggest_listen_port, suggest_server_address, update_tunnel_config, wg_quick,
     },
 };
 
+/// Runs `op` on each item until cancelled, collecting per-item results.
+fn bulk<T>(
+    cancel: &CancelFlag,
+    items: Vec<T>,
+    op: impl Fn(&T) -> (String, Result<(), Error>),
+) -> Vec<BulkResult> {
+    items
+        .iter()
+        .take_while(|_| !cancel.is_cancelled())
+        .map(|item| {
+            let (name, result) = op(item);
+            BulkResult {
+                name,
+                error: result.err().map(|e| e.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Applies the outcome of a background task to the app on the UI thread.
+type Completion = Box<dyn FnOnce(&mut App) + Send>;
+
+/// Cursor and pending edit of the settings screen.
+#[derive(Debug, Default)]
+struct SettingsScreen {
+    selected: usize,
+    editing: Option<String>,
+    status: Option<Message>,
+}
+
+/// Tunnels to bring up once the user accepts the warnings about them.
+struct ConfirmUp {
+    names: Vec<String>,
+    conflicts: Vec<(String, Vec<RouteConflict>)>,
+    full_tunnel: Vec<String>,
+}
+
+/// Entries moved by PageUp/PageDown in the activity log.
+const LOG_PAGE: usize = 10;
+
+/// Maximum gap between two clicks on the same row to count as a double-click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Identifies the selected list row across rebuilds.
+enum RowKey {
+    Tunnel(String),
+    Group(String),
+}
+
 pub struct App {
     tunnels: Vec<Tunnel>,
     list_state: ListState,
+    rows: Vec<ListRow>,
+    /// Number of tunnels passing the filter; a tunnel may span several rows.
+    match_count: usize,
+    collapsed: HashSet<String>,
+    marked: HashSet<String>,
+    /// Row where visual mode started; rows up to the cursor count as marked.
+    visual_anchor: Option<usize>,
+    bulk_results: Option<(String, Vec<BulkResult>)>,
+    /// Checklist of the last diagnostics run, with the tunnel name.
+    diagnostics: Option<(String, Vec<DiagnosticCheck>)>,
+    /// Result of the last MTU probe, with the tunnel name.
+    mtu_probe: Option<(String, MtuProbe)>,
+    /// Tunnels to export instead of all of them.
+    export_names: Option<Vec<String>>,
+    tags_input: Option<String>,
+    settings_screen: Option<SettingsScreen>,
+    filter: TunnelFilter,
+    state: State,
+    keymap: Keymap,
+    /// Set by `--read-only`: mutating keys are unbound and refused.
+    read_only: bool,
+    searching: bool,
     show_details: bool,
     show_help: bool,
-    confirm_delete: bool,
-    confirm_full_tunnel: Option<String>,
+    /// Tunnels waiting for delete confirmation.
+    confirm_delete: Option<Vec<String>>,
+    confirm_up: Option<ConfirmUp>,
     show_add_menu: bool,
     input_path: Option<String>,
     export_path: Option<String>,
+    rename_input: Option<String>,
     new_tunnel: Option<NewTunnelWizard>,
     edit_form: Option<EditFormState>,
+    raw_editor: Option<RawEditorState>,
+    external_edit: bool,
     pending_peer: Option<PendingPeerConfig>,
     peer_endpoint_input: Option<String>,
     peer_dns_input: Option<String>,
     peer_config: Option<PeerConfigState>,
     peer_save_path: Option<String>,
+    split_tunnel_input: Option<String>,
     message: Option<Message>,
+    activity: ActivityLog,
+    /// Lines scrolled up from the newest entry while the log popup is open.
+    log_scroll: Option<usize>,
+    /// The long-running operation in progress, shown with a spinner.
+    task: Option<Task<Completion>>,
+    /// Tunnels being loaded by a background refresh.
+    pending_refresh: Option<Receiver<Vec<Tunnel>>>,
+    last_refresh: Instant,
+    refresh_paused: bool,
+    /// Where the list and details pane were last drawn, for mouse hit tests.
+    list_area: Rect,
+    details_area: Option<Rect>,
+    details_scroll: u16,
+    /// Time and row of the last left click, to detect double-clicks.
+    last_click: Option<(Instant, usize)>,
     pub should_quit: bool,
 }
 
@@ -57,46 +180,343 @@ impl Default for App {
 
 impl App {
     pub fn new() -> Self {
+        let mut load_errors = Vec::new();
+        let keymap = Keymap::load().unwrap_or_else(|e| {
+            load_errors.push(format!("Using default keys: {e}"));
+            Keymap::default()
+        });
+        let read_only = privileged::is_read_only();
+        let keymap = if read_only {
+            keymap.without_mutating()
+        } else {
+            keymap
+        };
+        set_theme(Theme::load().unwrap_or_else(|e| {
+            load_errors.push(format!("Using default theme: {e}"));
+            Theme::DARK
+        }));
         let mut app = Self {
             tunnels: Vec::new(),
             list_state: ListState::default(),
+            rows: Vec::new(),
+            match_count: 0,
+            collapsed: HashSet::new(),
+            marked: HashSet::new(),
+            visual_anchor: None,
+            bulk_results: None,
+            diagnostics: None,
+            mtu_probe: None,
+            export_names: None,
+            tags_input: None,
+            settings_screen: None,
+            filter: TunnelFilter::default(),
+            state: State::load(),
+            keymap,
+            read_only,
+            searching: false,
             show_details: false,
             show_help: false,
-            confirm_delete: false,
-            confirm_full_tunnel: None,
+            confirm_delete: None,
+            confirm_up: None,
             show_add_menu: false,
             input_path: None,
             export_path: None,
+            rename_input: None,
             new_tunnel: None,
             edit_form: None,
+            raw_editor: None,
+            external_edit: false,
             pending_peer: None,
             peer_endpoint_input: None,
             peer_dns_input: None,
             peer_config: None,
             peer_save_path: None,
+            split_tunnel_input: None,
             message: None,
+            activity: ActivityLog::default(),
+            log_scroll: None,
+            task: None,
+            pending_refresh: None,
+            last_refresh: Instant::now(),
+            refresh_paused: false,
+            list_area: Rect::default(),
+            details_area: None,
+            details_scroll: 0,
+            last_click: None,
             should_quit: false,
         };
         app.refresh_tunnels();
         if !app.tunnels.is_empty() {
             app.list_state.select(Some(0));
         }
+        if let Err(e) = app.activity.set_file(settings().log_file.as_deref()) {
+            load_errors.push(format!("Not writing the activity log: {e}"));
+        }
+        if !privileged::is_elevated() && !read_only {
+            load_errors.push(
+                "Running without root: only readable tunnels are shown and changes will fail"
+                    .into(),
+            );
+        }
+        if let Some(dir) = privileged::helper_config_dir()
+            && dir != settings().config_dir
+        {
+            load_errors.push(format!(
+                "config_dir is ignored: the root helper uses {} from /etc/wg-tui/config.toml",
+                dir.display()
+            ));
+        }
+        if !load_errors.is_empty() {
+            app.notify(Message::Error(load_errors.join("; ")));
+        }
         app
     }
 
     pub fn refresh_tunnels(&mut self) {
-        self.tunnels = discover_tunnels();
-        for t in &mut self.tunnels {
-            t.is_active = is_interface_active(&t.name);
-            if t.is_active {
-                t.interface = get_interface_info(&t.name);
+        self.set_tunnels(load_tunnels());
+    }
+
+    fn set_tunnels(&mut self, tunnels: Vec<Tunnel>) {
+        let selected = self.selected_key();
+        self.tunnels = tunnels;
+        // Anything still loading in the background is older than this.
+        self.pending_refresh = None;
+        self.last_refresh = Instant::now();
+        self.apply_filter(selected);
+    }
+
+    /// Starts a background refresh when the interval has passed and picks up
+    /// a finished one. Results are dropped while a popup is open, since popups
+    /// act on the selected tunnel.
+    fn poll_auto_refresh(&mut self) {
+        if let Some(ref rx) = self.pending_refresh {
+            match rx.try_recv() {
+                Ok(tunnels) if !self.has_popup() => self.set_tunnels(tunnels),
+                Ok(_) | Err(TryRecvError::Disconnected) => self.pending_refresh = None,
+                Err(TryRecvError::Empty) => {}
+            }
+            return;
+        }
+        let interval = settings().refresh_interval;
+        if self.refresh_paused
+            || interval == 0
+            || self.has_popup()
+            || self.last_refresh.elapsed() < Duration::from_secs(interval)
+        {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || tx.send(load_tunnels()));
+        self.pending_refresh = Some(rx);
+        self.last_refresh = Instant::now();
+    }
+
+    /// Rebuilds the list rows, keeping `keep` selected if it is still shown.
+    fn apply_filter(&mut self, keep: Option<RowKey>) {
+        self.state.sort.sort(&mut self.tunnels);
+        let matches = self.filter.apply(&self.tunnels);
+        self.match_count = matches.len();
+        self.rows = group_rows(&self.tunnels, &matches, &self.collapsed);
+        match keep.and_then(|key| self.row_position(&key)) {
+            Some(i) => self.list_state.select(Some(i)),
+            None => self.clamp_selection(),
+        }
+    }
+
+    fn update_filter(&mut self, update: impl FnOnce(&mut TunnelFilter)) {
+        let selected = self.selected_key();
+        update(&mut self.filter);
+        self.apply_filter(selected);
+    }
+
+    fn selected_key(&self) -> Option<RowKey> {
+        match self.rows.get(self.list_state.selected()?)? {
+            ListRow::Group { tag, .. } => Some(RowKey::Group(tag.clone())),
+            ListRow::Tunnel(i) => Some(RowKey::Tunnel(self.tunnels[*i].name.clone())),
+        }
+    }
+
+    fn row_position(&self, key: &RowKey) -> Option<usize> {
+        self.rows.iter().position(|row| match (row, key) {
+            (ListRow::Group { tag, .. }, RowKey::Group(name)) => tag == name,
+            (ListRow::Tunnel(i), RowKey::Tunnel(name)) => self.tunnels[*i].name == *name,
+            _ => false,
+        })
+    }
+
+    /// The tag and member indices of the selected group header.
+    fn selected_group(&self) -> Option<(&str, &[usize])> {
+        match self.rows.get(self.list_state.selected()?)? {
+            ListRow::Group { tag, members } => Some((tag, members)),
+            ListRow::Tunnel(_) => None,
+        }
+    }
+
+    fn set_group_collapsed(&mut self, collapsed: bool) {
+        let Some((tag, _)) = self.selected_group() else {
+            return;
+        };
+        let tag = tag.to_string();
+        if collapsed {
+            self.collapsed.insert(tag.clone());
+        } else {
+            self.collapsed.remove(&tag);
+        }
+        self.apply_filter(Some(RowKey::Group(tag)));
+    }
+
+    fn bulk_targets(&self) -> Vec<String> {
+        bulk_targets(
+            &self.tunnels,
+            &self.rows,
+            &self.marked,
+            self.visual_range(),
+            self.list_state.selected(),
+        )
+    }
+
+    fn marked_names(&self) -> Vec<String> {
+        marked_names(&self.tunnels, &self.rows, &self.marked, self.visual_range())
+    }
+
+    fn visual_range(&self) -> Option<std::ops::RangeInclusive<usize>> {
+        let anchor = self.visual_anchor?;
+        let cursor = self.list_state.selected()?;
+        Some(anchor.min(cursor)..=anchor.max(cursor))
+    }
+
+    /// Marks or unmarks the selected tunnel, or every member of the selected
+    /// group, then moves down.
+    fn toggle_mark(&mut self) {
+        let names = match self.selected_group() {
+            Some((_, members)) => members
+                .iter()
+                .map(|&i| self.tunnels[i].name.clone())
+                .collect(),
+            None => match self.selected() {
+                Some(t) => vec![t.name.clone()],
+                None => return,
+            },
+        };
+        if names.iter().all(|n| self.marked.contains(n)) {
+            for name in &names {
+                self.marked.remove(name);
+            }
+        } else {
+            self.marked.extend(names);
+        }
+        self.move_selection(1);
+    }
+
+    /// Leaves visual mode, keeping the covered tunnels marked.
+    fn end_visual(&mut self) {
+        self.marked.extend(self.marked_names());
+        self.visual_anchor = None;
+    }
+
+    /// Brings the bulk targets up or down, skipping tunnels already in that
+    /// state.
+    fn set_targets_active(&mut self, up: bool) {
+        let names: Vec<String> = self
+            .bulk_targets()
+            .into_iter()
+            .filter(|name| {
+                self.tunnels
+                    .iter()
+                    .any(|t| &t.name == name && t.is_active != up)
+            })
+            .collect();
+        if names.is_empty() {
+            let action = if up { "up" } else { "down" };
+            self.notify(Message::Info(format!("Nothing to bring {action}")));
+        } else if up {
+            self.bring_up(names);
+        } else {
+            self.run_wg_quick("down", names);
+        }
+    }
+
+    /// Brings `names` up, first asking for confirmation if any of them
+    /// overlaps routes already in use or routes all traffic into the tunnel.
+    /// The checks read the routing table, so they run in the background.
+    fn bring_up(&mut self, names: Vec<String>) {
+        let label = match names.as_slice() {
+            [name] => format!("Checking routes of '{name}'"),
+            _ => format!("Checking routes of {} tunnels", names.len()),
+        };
+        self.run_task(label, move |cancel| {
+            let mut conflicts = Vec::new();
+            let mut full_tunnel = Vec::new();
+            for name in names.iter().take_while(|_| !cancel.is_cancelled()) {
+                let found = find_route_conflicts(name);
+                if !found.is_empty() {
+                    conflicts.push((name.clone(), found));
+                }
+                if is_full_tunnel_config(name) {
+                    full_tunnel.push(name.clone());
+                }
+            }
+            let cancelled = cancel.is_cancelled();
+            Box::new(move |app: &mut App| {
+                if cancelled {
+                    app.notify(Message::Info("Enable cancelled".into()));
+                } else if conflicts.is_empty() && full_tunnel.is_empty() {
+                    app.run_wg_quick("up", names);
+                } else {
+                    app.confirm_up = Some(ConfirmUp {
+                        names,
+                        conflicts,
+                        full_tunnel,
+                    });
+                }
+            })
+        });
+    }
+
+    /// Runs `wg-quick <action>` on each of `names` in the background.
+    fn run_wg_quick(&mut self, action: &'static str, names: Vec<String>) {
+        let label = match names.as_slice() {
+            [name] => format!("Bringing '{name}' {action}"),
+            _ => format!("Bringing {} tunnels {action}", names.len()),
+        };
+        self.run_task(label, move |cancel| {
+            let results = bulk(cancel, names, |name| (name.clone(), wg_quick(action, name)));
+            Box::new(move |app: &mut App| {
+                app.report_bulk(&format!("Bring {action}"), results);
+                app.refresh_tunnels();
+            })
+        });
+    }
+
+    /// Reports a single result in the status bar and several in a popup.
+    fn report_bulk(&mut self, title: &str, results: Vec<BulkResult>) {
+        if let [result] = results.as_slice() {
+            self.notify(match &result.error {
+                None => Message::Success(format!("{title}: '{}' done", result.name)),
+                Some(e) => Message::Error(format!("{title}: '{}' failed: {e}", result.name)),
+            });
+        } else {
+            for result in &results {
+                self.activity.push(match &result.error {
+                    None => Message::Success(format!("{title}: '{}' done", result.name)),
+                    Some(e) => Message::Error(format!("{title}: '{}' failed: {e}", result.name)),
+                });
             }
+            self.marked.clear();
+            self.bulk_results = Some((title.to_string(), results));
+        }
+    }
+
+    fn cycle_sort(&mut self) {
+        self.state.sort = self.state.sort.next();
+        self.update_filter(|_| {});
+        if let Err(e) = self.state.save() {
+            self.notify(Message::Error(format!("Could not save sort mode: {e}")));
         }
-        self.clamp_selection();
     }
 
     fn clamp_selection(&mut self) {
-        let selected = match (self.list_state.selected(), self.tunnels.len()) {
+        let selected = match (self.list_state.selected(), self.rows.len()) {
             (_, 0) => None,
             (None | Some(0), _) => Some(0),
             (Some(i), len) => Some(i.min(len - 1)),
@@ -105,90 +525,249 @@ impl App {
     }
 
     fn selected(&self) -> Option<&Tunnel> {
-        self.list_state.selected().and_then(|i| self.tunnels.get(i))
+        match self.rows.get(self.list_state.selected()?)? {
+            ListRow::Tunnel(i) => self.tunnels.get(*i),
+            ListRow::Group { .. } => None,
+        }
+    }
+
+    fn select_tunnel(&mut self, name: &str) {
+        if let Some(i) = self.row_position(&RowKey::Tunnel(name.into())) {
+            self.list_state.select(Some(i));
+        }
     }
 
     fn move_selection(&mut self, delta: isize) {
+        self.details_scroll = 0;
         if let Some(i) = self.list_state.selected() {
-            let new = (i as isize + delta).clamp(0, self.tunnels.len().saturating_sub(1) as isize);
+            let new = (i as isize + delta).clamp(0, self.rows.len().saturating_sub(1) as isize);
             self.list_state.select(Some(new as usize));
         }
     }
 
     fn toggle_selected(&mut self) {
-        let Some(tunnel) = self.selected() else {
-            return;
+        if let Some(tunnel) = self.selected() {
+            let name = tunnel.name.clone();
+            self.toggle_tunnel(name);
+        }
+    }
+
+    fn toggle_tunnel(&mut self, name: String) {
+        let active = self.tunnels.iter().any(|t| t.name == name && t.is_active);
+        if active {
+            self.run_wg_quick("down", vec![name]);
+        } else {
+            self.bring_up(vec![name]);
+        }
+    }
+
+    fn delete_tunnels(&mut self, names: &[String]) {
+        let targets: Vec<_> = names
+            .iter()
+            .map(|name| {
+                let active = self.tunnels.iter().any(|t| &t.name == name && t.is_active);
+                (name.clone(), active)
+            })
+            .collect();
+        let label = match names {
+            [name] => format!("Deleting '{name}'"),
+            _ => format!("Deleting {} tunnels", names.len()),
         };
-        let (name, active) = (tunnel.name.clone(), tunnel.is_active);
+        self.run_task(label, move |cancel| {
+            let results = bulk(cancel, targets, |(name, active)| {
+                (name.clone(), delete_tunnel(name, *active))
+            });
+            Box::new(move |app: &mut App| {
+                for result in &results {
+                    app.marked.remove(&result.name);
+                }
+                app.report_bulk("Delete", results);
+                app.refresh_tunnels();
+            })
+        });
+    }
 
-        if !active && is_full_tunnel_config(&name) {
-            self.confirm_full_tunnel = Some(name);
+    /// Runs `work` on a worker thread and applies the completion it returns
+    /// once done. Only one task runs at a time.
+    fn run_task(
+        &mut self,
+        label: String,
+        work: impl FnOnce(&CancelFlag) -> Completion + Send + 'static,
+    ) {
+        if let Some(ref task) = self.task {
+            self.notify(Message::Error(format!("Busy: {}", task.label)));
             return;
         }
-
-        self.toggle_selected_with_name(&name);
+        self.task = Some(Task::spawn(label, work));
     }
 
-    fn toggle_selected_with_name(&mut self, name: &str) {
-        let active = self
-            .tunnels
-            .iter()
-            .find(|t| t.name == name)
-            .map(|t| t.is_active)
-            .unwrap_or(false);
-
-        match wg_quick(if active { "down" } else { "up" }, name) {
-            Ok(()) => {
-                self.message = Some(Message::Success(format!(
-                    "Tunnel '{name}' {}",
-                    if active { "stopped" } else { "started" }
-                )));
+    fn poll_task(&mut self) {
+        let Some(result) = self.task.as_ref().and_then(Task::poll) else {
+            return;
+        };
+        self.task = None;
+        match result {
+            Ok(complete) => complete(self),
+            Err(e) => {
+                self.notify(Message::Error(e.to_string()));
                 self.refresh_tunnels();
             }
-            Err(e) => self.message = Some(Message::Error(e.to_string())),
         }
     }
 
-    fn delete_selected(&mut self) {
-        let Some(tunnel) = self.selected() else {
+    /// Asks the running task to stop. It stays in place until the worker
+    /// reports back, so its result is still applied and no other task can
+    /// start on the same tunnels meanwhile.
+    fn cancel_task(&mut self) {
+        let Some(ref task) = self.task else {
             return;
         };
-        let (name, active) = (tunnel.name.clone(), tunnel.is_active);
-
-        match delete_tunnel(&name, active) {
-            Ok(()) => {
-                self.message = Some(Message::Success(format!("Tunnel '{name}' deleted")));
-                self.refresh_tunnels();
-            }
-            Err(e) => self.message = Some(Message::Error(e.to_string())),
+        if task.is_cancelled() {
+            return;
         }
+        task.cancel();
+        let label = task.label.clone();
+        self.notify(Message::Info(format!("Cancelling: {label}")));
     }
 
     pub fn handle_events(&mut self) -> Result<(), Error> {
+        self.poll_task();
+        self.poll_auto_refresh();
         if !event::poll(Duration::from_millis(100))? {
             return Ok(());
         }
 
-        let Event::Key(key) = event::read()? else {
-            return Ok(());
-        };
-        if key.kind != KeyEventKind::Press {
-            return Ok(());
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => self.handle_key(key),
+            Event::Mouse(mouse) => {
+                self.handle_mouse(mouse);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// True while a popup or prompt covers the list and takes all input.
+    fn has_popup(&self) -> bool {
+        self.show_help
+            || self.show_add_menu
+            || self.searching
+            || self.bulk_results.is_some()
+            || self.diagnostics.is_some()
+            || self.mtu_probe.is_some()
+            || self.confirm_delete.is_some()
+            || self.confirm_up.is_some()
+            || self.input_path.is_some()
+            || self.export_path.is_some()
+            || self.rename_input.is_some()
+            || self.tags_input.is_some()
+            || self.settings_screen.is_some()
+            || self.log_scroll.is_some()
+            || self.new_tunnel.is_some()
+            || self.edit_form.is_some()
+            || self.raw_editor.is_some()
+            || self.pending_peer.is_some()
+            || self.peer_endpoint_input.is_some()
+            || self.peer_dns_input.is_some()
+            || self.peer_config.is_some()
+            || self.peer_save_path.is_some()
+            || self.split_tunnel_input.is_some()
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let (column, row) = (mouse.column, mouse.row);
+        if let Some(ref mut form) = self.edit_form {
+            // The split-tunnel prompt is drawn on top of the form.
+            if self.split_tunnel_input.is_none()
+                && mouse.kind == MouseEventKind::Down(MouseButton::Left)
+                && let Ok((width, height)) = terminal::size()
+                && let Some(field) =
+                    edit_form_field_at(Rect::new(0, 0, width, height), form, column, row)
+            {
+                form.focused_field = field;
+            }
+            return;
+        }
+        if self.has_popup() {
+            return;
         }
 
-        self.handle_key(key)
+        let in_details = self
+            .details_area
+            .is_some_and(|area| area.contains((column, row).into()));
+        match mouse.kind {
+            MouseEventKind::ScrollDown if in_details => {
+                self.details_scroll = self.details_scroll.saturating_add(1);
+            }
+            MouseEventKind::ScrollUp if in_details => {
+                self.details_scroll = self.details_scroll.saturating_sub(1);
+            }
+            MouseEventKind::ScrollDown => self.move_selection(1),
+            MouseEventKind::ScrollUp => self.move_selection(-1),
+            MouseEventKind::Down(MouseButton::Left) => self.click_list(column, row),
+            _ => {}
+        }
+    }
+
+    /// Selects the clicked row; a second click on the same row opens it like
+    /// Enter does.
+    fn click_list(&mut self, column: u16, row: u16) {
+        let area = self.list_area;
+        let inner = Rect::new(
+            area.x + 1,
+            area.y + 1,
+            area.width.saturating_sub(2),
+            area.height.saturating_sub(2),
+        );
+        if !inner.contains((column, row).into()) {
+            return;
+        }
+        let index = self.list_state.offset() + usize::from(row - inner.y);
+        if index >= self.rows.len() {
+            return;
+        }
+
+        let double = self
+            .last_click
+            .is_some_and(|(at, last)| last == index && at.elapsed() < DOUBLE_CLICK);
+        self.last_click = (!double).then(|| (Instant::now(), index));
+        if self.list_state.selected() != Some(index) {
+            self.details_scroll = 0;
+        }
+        self.list_state.select(Some(index));
+
+        if double {
+            self.open_selected();
+        }
     }
 
     fn handle_key(&mut self, key: crossterm::event::KeyEvent) -> Result<(), Error> {
         self.message = None;
 
+        if key.code == KeyCode::Esc && self.task.is_some() && !self.has_popup() {
+            self.cancel_task();
+            return Ok(());
+        }
+
         if self.consume_help() {
             return Ok(());
         }
+        if self.consume_activity_log(key) {
+            return Ok(());
+        }
+        if self.consume_bulk_results() {
+            return Ok(());
+        }
+        if self.consume_diagnostics() {
+            return Ok(());
+        }
+        if self.consume_mtu_probe(key) {
+            return Ok(());
+        }
         if self.consume_confirm_delete(key) {
             return Ok(());
         }
-        if self.consume_confirm_full_tunnel(key) {
+        if self.consume_confirm_up(key) {
             return Ok(());
         }
         if self.consume_peer_save_path(key) {
@@ -200,29 +779,68 @@ impl App {
         if self.consume_export_path(key) {
             return Ok(());
         }
+        if self.consume_rename_input(key) {
+            return Ok(());
+        }
+        if self.consume_settings_screen(key) {
+            return Ok(());
+        }
+        if self.consume_tags_input(key) {
+            return Ok(());
+        }
         if self.consume_peer_endpoint_input(key) {
             return Ok(());
         }
         if self.consume_peer_dns_input(key) {
             return Ok(());
         }
+        if self.consume_split_tunnel_input(key) {
+            return Ok(());
+        }
         if self.consume_new_tunnel_wizard(key) {
             return Ok(());
         }
         if self.consume_peer_config(key) {
             return Ok(());
         }
+        if self.consume_raw_editor(key) {
+            return Ok(());
+        }
         if self.consume_edit_form(key) {
             return Ok(());
         }
         if self.consume_add_menu(key) {
             return Ok(());
         }
+        if self.consume_search(key) {
+            return Ok(());
+        }
 
         self.handle_global_key(key);
         Ok(())
     }
 
+    fn consume_search(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        if !self.searching {
+            return false;
+        }
+        match key.code {
+            KeyCode::Enter => self.searching = false,
+            KeyCode::Esc => {
+                self.searching = false;
+                self.update_filter(|f| f.query.clear());
+            }
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Backspace => self.update_filter(|f| {
+                f.query.pop();
+            }),
+            KeyCode::Char(c) => self.update_filter(|f| f.query.push(c)),
+            _ => {}
+        }
+        true
+    }
+
     fn consume_help(&mut self) -> bool {
         if self.show_help {
             self.show_help = false;
@@ -231,37 +849,83 @@ impl App {
         false
     }
 
-    fn consume_confirm_delete(&mut self, key: crossterm::event::KeyEvent) -> bool {
-        if !self.confirm_delete {
+    /// Shows `message` in the status bar and records it in the activity log.
+    fn notify(&mut self, message: Message) {
+        self.activity.push(message.clone());
+        self.message = Some(message);
+    }
+
+    fn consume_activity_log(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some(ref mut scroll) = self.log_scroll else {
             return false;
+        };
+        let newest = self.activity.entries().len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.log_scroll = None,
+            KeyCode::Up | KeyCode::Char('k') => *scroll = (*scroll + 1).min(newest),
+            KeyCode::Down | KeyCode::Char('j') => *scroll = scroll.saturating_sub(1),
+            KeyCode::PageUp => *scroll = (*scroll + LOG_PAGE).min(newest),
+            KeyCode::PageDown => *scroll = scroll.saturating_sub(LOG_PAGE),
+            KeyCode::Char('g') | KeyCode::Home => *scroll = newest,
+            KeyCode::Char('G') | KeyCode::End => *scroll = 0,
+            _ => {}
         }
+        true
+    }
+
+    fn consume_bulk_results(&mut self) -> bool {
+        self.bulk_results.take().is_some()
+    }
+
+    fn consume_diagnostics(&mut self) -> bool {
+        self.diagnostics.take().is_some()
+    }
+
+    fn consume_mtu_probe(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some((name, probe)) = self.mtu_probe.take() else {
+            return false;
+        };
+        if key.code == KeyCode::Enter && !self.read_only {
+            self.apply_mtu(&name, probe.recommended);
+        }
+        true
+    }
+
+    /// Sets the `MTU` of a tunnel's config, restarting it if active.
+    fn apply_mtu(&mut self, name: &str, mtu: u16) {
+        let was_active = self.tunnels.iter().any(|t| t.name == name && t.is_active);
+        let result = parse_tunnel_config(name).and_then(|mut draft| {
+            let index = EDIT_INTERFACE_KEYS
+                .iter()
+                .position(|k| *k == "MTU")
+                .expect("MTU is an interface key");
+            draft.interface[index] = mtu.to_string();
+            update_tunnel_config(&draft)
+        });
+        match result {
+            Ok(()) => self.finish_config_save(name, was_active),
+            Err(e) => self.notify(Message::Error(e.to_string())),
+        }
+    }
+
+    fn consume_confirm_delete(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some(names) = self.confirm_delete.take() else {
+            return false;
+        };
         match key.code {
-            KeyCode::Char('y') | KeyCode::Char('Y') => {
-                self.confirm_delete = false;
-                self.delete_selected();
-            }
-            _ => {
-                self.confirm_delete = false;
-                self.message = Some(Message::Info("Delete cancelled".into()));
-            }
+            KeyCode::Char('y') | KeyCode::Char('Y') => self.delete_tunnels(&names),
+            _ => self.notify(Message::Info("Delete cancelled".into())),
         }
         true
     }
 
-    fn consume_confirm_full_tunnel(&mut self, key: crossterm::event::KeyEvent) -> bool {
-        let Some(ref name) = self.confirm_full_tunnel else {
+    fn consume_confirm_up(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some(confirm) = self.confirm_up.take() else {
             return false;
         };
         match key.code {
-            KeyCode::Char('y') | KeyCode::Char('Y') => {
-                let name = name.clone();
-                self.confirm_full_tunnel = None;
-                self.toggle_selected_with_name(&name);
-            }
-            _ => {
-                self.confirm_full_tunnel = None;
-                self.message = Some(Message::Info("Enable cancelled".into()));
-            }
+            KeyCode::Char('y') | KeyCode::Char('Y') => self.run_wg_quick("up", confirm.names),
+            _ => self.notify(Message::Info("Enable cancelled".into())),
         }
         true
     }
@@ -279,22 +943,22 @@ impl App {
                 };
                 let dest = expand_path(&path_str);
                 if dest.exists() {
-                    self.message = Some(Message::Error("File already exists".into()));
+                    self.notify(Message::Error("File already exists".into()));
                     return true;
                 }
                 match fs::write(&dest, &peer.config_text) {
                     Ok(()) => {
-                        self.message = Some(Message::Success(format!(
+                        self.notify(Message::Success(format!(
                             "Peer config saved to {}",
                             dest.display()
                         )));
                     }
-                    Err(e) => self.message = Some(Message::Error(e.to_string())),
+                    Err(e) => self.notify(Message::Error(e.to_string())),
                 }
             }
             KeyCode::Esc => {
                 self.peer_save_path = None;
-                self.message = Some(Message::Info("Save cancelled".into()));
+                self.notify(Message::Info("Save cancelled".into()));
             }
             KeyCode::Backspace => {
                 path.pop();
@@ -317,15 +981,15 @@ impl App {
                 self.input_path = None;
                 match import_tunnel(&path_str) {
                     Ok(name) => {
-                        self.message = Some(Message::Success(format!("Tunnel '{name}' imported")));
+                        self.notify(Message::Success(format!("Tunnel '{name}' imported")));
                         self.refresh_tunnels();
                     }
-                    Err(e) => self.message = Some(Message::Error(e.to_string())),
+                    Err(e) => self.notify(Message::Error(e.to_string())),
                 }
             }
             KeyCode::Esc => {
                 self.input_path = None;
-                self.message = Some(Message::Info("Import cancelled".into()));
+                self.notify(Message::Info("Import cancelled".into()));
             }
             KeyCode::Backspace => {
                 path.pop();
@@ -338,6 +1002,162 @@ impl App {
         true
     }
 
+    fn consume_settings_screen(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some(ref mut screen) = self.settings_screen else {
+            return false;
+        };
+        let (name, _, _) = SETTINGS[screen.selected];
+        if let Some(ref mut value) = screen.editing {
+            match key.code {
+                KeyCode::Enter => {
+                    let value = value.clone();
+                    match self.change_setting(name, &value) {
+                        Ok(status) => {
+                            let screen = self.settings_screen.as_mut().expect("screen is open");
+                            screen.editing = None;
+                            screen.status = Some(status);
+                        }
+                        Err(e) => {
+                            let screen = self.settings_screen.as_mut().expect("screen is open");
+                            screen.status = Some(Message::Error(e.to_string()));
+                        }
+                    }
+                }
+                KeyCode::Esc => {
+                    screen.editing = None;
+                    screen.status = None;
+                }
+                KeyCode::Backspace => {
+                    value.pop();
+                }
+                KeyCode::Char(c) => value.push(c),
+                _ => {}
+            }
+            return true;
+        }
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.settings_screen = None,
+            KeyCode::Down | KeyCode::Char('j') => {
+                screen.selected = (screen.selected + 1).min(SETTINGS.len() - 1);
+            }
+            KeyCode::Up | KeyCode::Char('k') => screen.selected = screen.selected.saturating_sub(1),
+            KeyCode::Enter => {
+                screen.editing = Some(settings().get(name));
+                screen.status = None;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Validates and applies one setting, then saves it to the user's
+    /// settings file.
+    fn change_setting(&mut self, name: &str, value: &str) -> Result<Message, Error> {
+        let mut updated = settings();
+        updated.set(name, value)?;
+        let path = updated.save_value(name)?;
+        set_settings(updated);
+        match name {
+            "theme" => set_theme(Theme::load()?),
+            "log_file" => self.activity.set_file(settings().log_file.as_deref())?,
+            // The root helper only takes its directory from /etc/wg-tui.
+            "config_dir" if privileged::has_helper() => {
+                return Ok(Message::Info(format!(
+                    "Saved to {}; the root helper keeps using the config_dir of \
+                     /etc/wg-tui/config.toml",
+                    path.display()
+                )));
+            }
+            "config_dir" => self.refresh_tunnels(),
+            "mouse" if settings().mouse => {
+                crossterm::execute!(std::io::stdout(), EnableMouseCapture)?;
+            }
+            "mouse" => crossterm::execute!(std::io::stdout(), DisableMouseCapture)?,
+            _ => {}
+        }
+        Ok(Message::Success(format!("Saved to {}", path.display())))
+    }
+
+    fn consume_rename_input(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some(ref mut new_name) = self.rename_input else {
+            return false;
+        };
+        match key.code {
+            KeyCode::Enter => {
+                let new_name = new_name.trim().to_string();
+                self.rename_input = None;
+                let Some(tunnel) = self.selected() else {
+                    return true;
+                };
+                let old_name = tunnel.name.clone();
+                let active = tunnel.is_active;
+                self.run_task(format!("Renaming '{old_name}'"), move |_| {
+                    let result = rename_tunnel(&old_name, &new_name, active);
+                    Box::new(move |app: &mut App| match result {
+                        Ok(()) => {
+                            app.notify(Message::Success(format!(
+                                "Tunnel '{old_name}' renamed to '{new_name}'"
+                            )));
+                            app.refresh_tunnels();
+                            app.select_tunnel(&new_name);
+                        }
+                        Err(e) => {
+                            app.notify(Message::Error(e.to_string()));
+                            app.refresh_tunnels();
+                        }
+                    })
+                });
+            }
+            KeyCode::Esc => {
+                self.rename_input = None;
+                self.notify(Message::Info("Rename cancelled".into()));
+            }
+            KeyCode::Backspace => {
+                new_name.pop();
+            }
+            KeyCode::Char(c) => {
+                new_name.push(c);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn consume_tags_input(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some(ref mut tags) = self.tags_input else {
+            return false;
+        };
+        match key.code {
+            KeyCode::Enter => {
+                let tags = tags.clone();
+                self.tags_input = None;
+                let Some(tunnel) = self.selected() else {
+                    return true;
+                };
+                let name = tunnel.name.clone();
+                match set_tunnel_tags(&name, &tags) {
+                    Ok(()) => {
+                        self.notify(Message::Success(format!("Tags of '{name}' updated")));
+                        self.refresh_tunnels();
+                    }
+                    Err(e) => self.notify(Message::Error(e.to_string())),
+                }
+            }
+            KeyCode::Esc => {
+                self.tags_input = None;
+                self.notify(Message::Info("Tagging cancelled".into()));
+            }
+            KeyCode::Backspace => {
+                tags.pop();
+            }
+            KeyCode::Char(c) => {
+                tags.push(c);
+            }
+            _ => {}
+        }
+        true
+    }
+
     fn consume_export_path(&mut self, key: crossterm::event::KeyEvent) -> bool {
         let Some(ref mut path) = self.export_path else {
             return false;
@@ -346,20 +1166,21 @@ impl App {
             KeyCode::Enter => {
                 let path_str = path.clone();
                 self.export_path = None;
-                match export_tunnels_to_zip(&path_str) {
-                    Ok(dest) => {
-                        self.message = Some(Message::Success(format!(
-                            "Exported {} tunnels to {}",
-                            self.tunnels.len(),
+                let names = self.export_names.take();
+                match export_tunnels_to_zip(&path_str, names.as_deref()) {
+                    Ok((dest, count)) => {
+                        self.notify(Message::Success(format!(
+                            "Exported {count} tunnels to {}",
                             dest.display()
                         )));
                     }
-                    Err(e) => self.message = Some(Message::Error(e.to_string())),
+                    Err(e) => self.notify(Message::Error(e.to_string())),
                 }
             }
             KeyCode::Esc => {
                 self.export_path = None;
-                self.message = Some(Message::Info("Export cancelled".into()));
+                self.export_names = None;
+                self.notify(Message::Info("Export cancelled".into()));
             }
             KeyCode::Backspace => {
                 path.pop();
@@ -380,19 +1201,19 @@ impl App {
             KeyCode::Enter => {
                 let endpoint_str = endpoint.trim().to_string();
                 if endpoint_str.is_empty() {
-                    self.message = Some(Message::Error("Endpoint is required".into()));
+                    self.notify(Message::Error("Endpoint is required".into()));
                     return true;
                 }
                 if let Some(pending) = self.pending_peer.as_mut() {
                     pending.endpoint = endpoint_str;
                 }
                 self.peer_endpoint_input = None;
-                self.peer_dns_input = Some(String::new());
+                self.peer_dns_input = Some(settings().dns);
             }
             KeyCode::Esc => {
                 self.peer_endpoint_input = None;
                 self.pending_peer = None;
-                self.message = Some(Message::Info("Peer config cancelled".into()));
+                self.notify(Message::Info("Peer config cancelled".into()));
             }
             KeyCode::Backspace => {
                 endpoint.pop();
@@ -431,7 +1252,7 @@ impl App {
             KeyCode::Esc => {
                 self.peer_dns_input = None;
                 self.pending_peer = None;
-                self.message = Some(Message::Info("Peer config cancelled".into()));
+                self.notify(Message::Info("Peer config cancelled".into()));
             }
             KeyCode::Backspace => {
                 dns.pop();
@@ -444,6 +1265,60 @@ impl App {
         true
     }
 
+    fn consume_split_tunnel_input(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some(ref mut excluded) = self.split_tunnel_input else {
+            return false;
+        };
+        match key.code {
+            KeyCode::Enter => {
+                let excluded = excluded.clone();
+                let Some(allowed_ips) = self.focused_allowed_ips() else {
+                    self.split_tunnel_input = None;
+                    return true;
+                };
+                match split_tunnel(allowed_ips, &excluded) {
+                    Ok(result) => {
+                        self.split_tunnel_input = None;
+                        self.set_focused_allowed_ips(result);
+                        self.notify(Message::Success("AllowedIPs updated".into()));
+                    }
+                    Err(e) => self.notify(Message::Error(e)),
+                }
+            }
+            KeyCode::Esc => {
+                self.split_tunnel_input = None;
+            }
+            KeyCode::Backspace => {
+                excluded.pop();
+            }
+            KeyCode::Char(c) => {
+                excluded.push(c);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// The AllowedIPs value being edited, if the wizard or edit form has it
+    /// focused.
+    fn focused_allowed_ips(&self) -> Option<&str> {
+        if let Some(wizard) = &self.new_tunnel {
+            return wizard.edits_allowed_ips().then(|| wizard.current_value());
+        }
+        let form = self.edit_form.as_ref()?;
+        (form.focused_field == ALLOWED_IPS_FIELD)
+            .then(|| form.focused_input())?
+            .map(Input::value)
+    }
+
+    fn set_focused_allowed_ips(&mut self, value: String) {
+        if let Some(NewTunnelWizard::Client(wizard)) = &mut self.new_tunnel {
+            wizard.draft.allowed_ips = value;
+        } else if let Some(input) = self.edit_form.as_mut().and_then(|f| f.focused_input_mut()) {
+            *input = Input::new(value);
+        }
+    }
+
     fn consume_new_tunnel_wizard(&mut self, key: crossterm::event::KeyEvent) -> bool {
         let Some(ref mut wizard) = self.new_tunnel else {
             return false;
@@ -452,7 +1327,7 @@ impl App {
             KeyCode::Enter => {
                 let finished = {
                     if let Some(err) = wizard.validate_current() {
-                        self.message = Some(Message::Error(err));
+                        self.notify(Message::Error(err));
                         return true;
                     }
                     wizard.advance()
@@ -465,11 +1340,12 @@ impl App {
                             match create_tunnel(&draft) {
                                 Ok(()) => {
                                     let name = draft.name;
-                                    self.message =
-                                        Some(Message::Success(format!("Tunnel '{name}' created")));
+                                    self.notify(Message::Success(format!(
+                                        "Tunnel '{name}' created"
+                                    )));
                                     self.refresh_tunnels();
                                 }
-                                Err(e) => self.message = Some(Message::Error(e.to_string())),
+                                Err(e) => self.notify(Message::Error(e.to_string())),
                             }
                         }
                         NewTunnelWizard::Server(wizard) => {
@@ -477,11 +1353,30 @@ impl App {
                             match create_server_tunnel(&draft) {
                                 Ok(()) => {
                                     let name = draft.name;
-                                    self.message =
-                                        Some(Message::Success(format!("Tunnel '{name}' created")));
+                                    self.notify(Message::Success(format!(
+                                        "Tunnel '{name}' created"
+                                    )));
                                     self.refresh_tunnels();
                                 }
-                                Err(e) => self.message = Some(Message::Error(e.to_string())),
+                                Err(e) => self.notify(Message::Error(e.to_string())),
+                            }
+                        }
+                        NewTunnelWizard::Clone(wizard) => {
+                            let draft = wizard.draft;
+                            match clone_tunnel(&draft) {
+                                Ok(public_key) => {
+                                    let name = draft.name.trim();
+                                    let text = match public_key {
+                                        Some(key) => format!(
+                                            "Tunnel '{name}' created, new public key: {key}"
+                                        ),
+                                        None => format!("Tunnel '{name}' created"),
+                                    };
+                                    self.notify(Message::Success(text));
+                                    self.refresh_tunnels();
+                                    self.select_tunnel(name);
+                                }
+                                Err(e) => self.notify(Message::Error(e.to_string())),
                             }
                         }
                     }
@@ -489,7 +1384,12 @@ impl App {
             }
             KeyCode::Esc => {
                 self.new_tunnel = None;
-                self.message = Some(Message::Info("Create cancelled".into()));
+                self.notify(Message::Info("Create cancelled".into()));
+            }
+            KeyCode::Char('x')
+                if key.modifiers.contains(KeyModifiers::CONTROL) && wizard.edits_allowed_ips() =>
+            {
+                self.split_tunnel_input = Some(String::new());
             }
             KeyCode::Backspace => {
                 wizard.current_value_mut().pop();
@@ -502,6 +1402,125 @@ impl App {
         true
     }
 
+    /// Restarts the tunnel in the background if it was running and reports
+    /// the result of a saved config change.
+    fn finish_config_save(&mut self, name: &str, was_active: bool) {
+        if !was_active {
+            self.notify(Message::Success("Config updated".into()));
+            self.refresh_tunnels();
+            return;
+        }
+        if let Some(ref task) = self.task {
+            let text = format!(
+                "Config updated; '{name}' not restarted while busy: {}",
+                task.label
+            );
+            self.notify(Message::Info(text));
+            return;
+        }
+        let name = name.to_string();
+        self.run_task(format!("Restarting '{name}'"), move |_| {
+            let result = reload_tunnel(&name);
+            Box::new(move |app: &mut App| {
+                app.notify(match result {
+                    Ok(()) => Message::Success("Config updated".into()),
+                    Err(e) => Message::Error(e.to_string()),
+                });
+                app.refresh_tunnels();
+            })
+        });
+    }
+
+    fn consume_raw_editor(&mut self, key: crossterm::event::KeyEvent) -> bool {
+        let Some(ref mut editor) = self.raw_editor else {
+            return false;
+        };
+        let confirm_discard = std::mem::take(&mut editor.confirm_discard);
+        editor.status = None;
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let modified = editor.is_modified();
+        let area = &mut editor.area;
+        match key.code {
+            KeyCode::Esc if confirm_discard || !modified => {
+                self.raw_editor = None;
+                self.notify(Message::Info("Edit cancelled".into()));
+            }
+            KeyCode::Esc => {
+                editor.confirm_discard = true;
+                editor.status = Some(Message::Info(
+                    "Unsaved changes. Press Esc again to discard them".into(),
+                ));
+            }
+            KeyCode::Char('s') if ctrl => {
+                let editor = self.raw_editor.take().unwrap();
+                match save_tunnel_config(&editor.tunnel_name, &editor.area.text()) {
+                    Ok(()) => self.finish_config_save(&editor.tunnel_name, editor.was_active),
+                    Err(e) => {
+                        self.raw_editor = Some(RawEditorState {
+                            status: Some(Message::Error(e.to_string())),
+                            ..editor
+                        });
+                    }
+                }
+            }
+            KeyCode::Char('r') if ctrl => editor.reveal_keys = !editor.reveal_keys,
+            KeyCode::Char('e') if ctrl => self.external_edit = true,
+            KeyCode::Char(c) if !ctrl => area.insert_char(c),
+            KeyCode::Enter => area.insert_newline(),
+            KeyCode::Backspace => area.backspace(),
+            KeyCode::Delete => area.delete(),
+            KeyCode::Left => area.move_left(),
+            KeyCode::Right => area.move_right(),
+            KeyCode::Up => area.move_vertical(-1),
+            KeyCode::Down => area.move_vertical(1),
+            KeyCode::PageUp => area.move_vertical(-(RAW_EDITOR_PAGE as isize)),
+            KeyCode::PageDown => area.move_vertical(RAW_EDITOR_PAGE as isize),
+            KeyCode::Home => area.move_home(),
+            KeyCode::End => area.move_end(),
+            _ => {}
+        }
+        true
+    }
+
+    /// Hands the terminal to `$EDITOR` when the raw editor asked for it, then
+    /// loads the result back into the raw editor for validation.
+    pub fn run_external_editor(&mut self, terminal: &mut DefaultTerminal) -> Result<(), Error> {
+        if !std::mem::take(&mut self.external_edit) {
+            return Ok(());
+        }
+        let Some(ref mut editor) = self.raw_editor else {
+            return Ok(());
+        };
+
+        crossterm::execute!(std::io::stdout(), DisableMouseCapture)?;
+        ratatui::restore();
+        let edited = edit_externally(&editor.tunnel_name, &editor.area.text());
+        terminal::enable_raw_mode()?;
+        crossterm::execute!(std::io::stdout(), terminal::EnterAlternateScreen)?;
+        if settings().mouse {
+            crossterm::execute!(std::io::stdout(), EnableMouseCapture)?;
+        }
+        terminal.clear()?;
+
+        editor.status = Some(match edited {
+            Ok(text) if text == editor.area.text() => Message::Info("No changes".into()),
+            Ok(text) => {
+                editor.area = TextArea::new(&text);
+                let errors = lint_config(&WgConfig::parse(&text))
+                    .into_iter()
+                    .filter(|l| l.severity == Severity::Error)
+                    .count();
+                if errors == 0 {
+                    Message::Success("Changes loaded. Press Ctrl+S to save".into())
+                } else {
+                    Message::Error(format!("Changes loaded with {errors} error(s)"))
+                }
+            }
+            Err(e) => Message::Error(e.to_string()),
+        });
+        Ok(())
+    }
+
     fn consume_edit_form(&mut self, key: crossterm::event::KeyEvent) -> bool {
         let Some(ref mut form) = self.edit_form else {
             return false;
@@ -519,35 +1538,44 @@ impl App {
             KeyCode::Down => {
                 form.next_field();
             }
-            KeyCode::Enter => {
-                let form = self.edit_form.take().unwrap();
-                match update_tunnel_config(&form.tunnel_name, &form.to_draft()) {
-                    Ok(()) => {
-                        if form.was_active {
-                            let name = form.tunnel_name.clone();
-                            if let Err(e) = wg_quick("down", &name) {
-                                self.message = Some(Message::Error(e.to_string()));
-                                return true;
-                            }
-                            if let Err(e) = wg_quick("up", &name) {
-                                self.message = Some(Message::Error(e.to_string()));
-                                return true;
-                            }
+            KeyCode::Enter => {
+                let mut form = self.edit_form.take().unwrap();
+                match update_tunnel_config(&form.to_draft()) {
+                    Ok(()) => self.finish_config_save(&form.tunnel_name, form.was_active),
+                    // Keep the form open on a field error so it can be fixed.
+                    Err(e) => {
+                        if let Some(field) = e.field() {
+                            form.mark_invalid(field);
+                            self.edit_form = Some(form);
                         }
-                        self.message = Some(Message::Success("Config updated".into()));
-                        self.refresh_tunnels();
+                        self.notify(Message::Error(e.to_string()));
                     }
-                    Err(e) => self.message = Some(Message::Error(e.to_string())),
                 }
             }
             KeyCode::Esc => {
                 self.edit_form = None;
-                self.message = Some(Message::Info("Edit cancelled".into()));
+                self.notify(Message::Info("Edit cancelled".into()));
+            }
+            KeyCode::Char('x')
+                if key.modifiers.contains(KeyModifiers::CONTROL)
+                    && form.focused_field == ALLOWED_IPS_FIELD =>
+            {
+                self.split_tunnel_input = Some(String::new());
             }
-            KeyCode::Char('t') => {
+            KeyCode::Char(' ') if form.focused_field == KILL_SWITCH_FIELD => {
+                form.kill_switch = !form.kill_switch;
+            }
+            KeyCode::PageDown => form.select_peer(1),
+            KeyCode::PageUp => form.select_peer(-1),
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                form.add_peer();
+            }
+            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                form.delete_peer();
+            }
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                 let tunnel_name = form.tunnel_name.clone();
-                let _ = form;
-                self.toggle_selected_with_name(&tunnel_name);
+                self.toggle_tunnel(tunnel_name);
             }
             _ => {
                 let request = match (key.code, key.modifiers) {
@@ -580,8 +1608,12 @@ impl App {
                     }
                     _ => None,
                 };
-                if let Some(req) = request {
-                    form.inputs[form.focused_field].handle(req);
+                if let Some(req) = request
+                    && let Some(input) = form.focused_input_mut()
+                    && input.handle(req).is_some_and(|change| change.value)
+                    && form.invalid_field == Some(form.focused_field)
+                {
+                    form.invalid_field = None;
                 }
             }
         }
@@ -604,7 +1636,7 @@ impl App {
                 }
                 Err(_) => {
                     peer.show_qr = false;
-                    self.message = Some(Message::Error("QR data is too large".into()));
+                    self.notify(Message::Error("QR data is too large".into()));
                 }
             },
             KeyCode::Char('b') => {
@@ -640,14 +1672,14 @@ impl App {
                 let private_key = match generate_private_key() {
                     Ok(key) => key,
                     Err(e) => {
-                        self.message = Some(Message::Error(e.to_string()));
+                        self.notify(Message::Error(e.to_string()));
                         return true;
                     }
                 };
                 self.new_tunnel = Some(NewTunnelWizard::server(
                     name,
                     address,
-                    "51820".into(),
+                    settings().listen_port.to_string(),
                     private_key,
                     egress,
                 ));
@@ -661,71 +1693,227 @@ impl App {
     }
 
     fn handle_global_key(&mut self, key: crossterm::event::KeyEvent) {
-        match (key.code, key.modifiers) {
-            (KeyCode::Char('q') | KeyCode::Esc, _) => self.should_quit = true,
-            (KeyCode::Char('c'), m) if m.contains(KeyModifiers::CONTROL) => self.should_quit = true,
-            (KeyCode::Char('j') | KeyCode::Down, _) => self.move_selection(1),
-            (KeyCode::Char('k') | KeyCode::Up, _) => self.move_selection(-1),
-            (KeyCode::Char('g'), _) => self.list_state.select(Some(0)),
-            (KeyCode::Char('G'), _) => self
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.should_quit = true;
+            return;
+        }
+        // Esc backs out of visual mode, marks and the filter before it reaches
+        // the keymap, where it quits by default.
+        if key.code == KeyCode::Esc {
+            if self.visual_anchor.is_some() {
+                self.end_visual();
+                return;
+            }
+            if !self.marked.is_empty() {
+                self.marked.clear();
+                return;
+            }
+            if !self.filter.is_empty() {
+                self.update_filter(|f| *f = TunnelFilter::default());
+                return;
+            }
+        }
+        let Some(action) = self.keymap.action(&key) else {
+            return;
+        };
+        match action {
+            Action::Visual if self.visual_anchor.is_some() => self.end_visual(),
+            Action::Visual => self.visual_anchor = self.list_state.selected(),
+            Action::Quit => self.should_quit = true,
+            Action::Search => self.searching = true,
+            Action::QuickFilter => self.update_filter(|f| f.quick = f.quick.next()),
+            Action::Sort => self.cycle_sort(),
+            Action::Down => self.move_selection(1),
+            Action::Up => self.move_selection(-1),
+            Action::First => self.list_state.select(Some(0)),
+            Action::Last => self
                 .list_state
-                .select(Some(self.tunnels.len().saturating_sub(1))),
-            (KeyCode::Enter, _) => {
+                .select(Some(self.rows.len().saturating_sub(1))),
+            Action::Edit => self.open_selected(),
+            Action::Collapse => self.set_group_collapsed(true),
+            Action::Expand => self.set_group_collapsed(false),
+            Action::BringUp => self.set_targets_active(true),
+            Action::BringDown => self.set_targets_active(false),
+            Action::Tags => {
+                if let Some(tunnel) = self.selected() {
+                    self.tags_input = Some(tunnel.tags.join(", "));
+                }
+            }
+            Action::EditRaw => {
                 let Some(tunnel) = self.selected() else {
                     return;
                 };
                 let was_active = tunnel.is_active;
-                match parse_tunnel_config(&tunnel.name) {
-                    Ok(draft) => {
-                        let form = EditFormState::new(tunnel.name.clone(), draft, was_active);
-                        self.edit_form = Some(form);
+                match read_tunnel_config(&tunnel.name) {
+                    Ok(content) => {
+                        self.raw_editor = Some(RawEditorState::new(
+                            tunnel.name.clone(),
+                            &content,
+                            was_active,
+                        ));
                     }
-                    Err(e) => self.message = Some(Message::Error(e.to_string())),
+                    Err(e) => self.notify(Message::Error(e.to_string())),
                 }
             }
-            (KeyCode::Char(' '), _) => self.toggle_selected(),
-            (KeyCode::Char('d'), _) => self.show_details = !self.show_details,
-            (KeyCode::Char('x'), _) => {
-                if self.selected().is_some() {
-                    self.confirm_delete = true;
+            Action::Rename => {
+                if let Some(tunnel) = self.selected() {
+                    self.rename_input = Some(tunnel.name.clone());
                 }
             }
-            (KeyCode::Char('a'), _) => self.show_add_menu = true,
-            (KeyCode::Char('p'), _) => {
+            Action::Clone => {
+                let Some(tunnel) = self.selected() else {
+                    return;
+                };
+                let source = tunnel.name.clone();
+                let listen_port = if has_listen_port(&source) {
+                    suggest_listen_port().to_string()
+                } else {
+                    String::new()
+                };
+                let name = self.default_tunnel_name();
+                self.new_tunnel = Some(NewTunnelWizard::clone_of(source, name, listen_port));
+            }
+            Action::Mark => self.toggle_mark(),
+            Action::Details => self.show_details = !self.show_details,
+            Action::Diagnose => self.run_diagnostics(),
+            Action::ProbeMtu => self.run_mtu_probe(),
+            Action::Delete => {
+                let names = self.marked_names();
+                let names = if names.is_empty() {
+                    self.selected().map(|t| vec![t.name.clone()])
+                } else {
+                    Some(names)
+                };
+                self.end_visual();
+                self.confirm_delete = names;
+            }
+            Action::Add => self.show_add_menu = true,
+            Action::AddPeer => {
                 let Some(tunnel) = self.selected() else {
                     return;
                 };
-                match add_server_peer(&tunnel.name) {
-                    Ok(peer) => {
+                let name = tunnel.name.clone();
+                self.run_task(format!("Adding peer to '{name}'"), move |_| {
+                    let result = add_server_peer(&name).map(|peer| {
                         let endpoint = detect_public_ip()
                             .map(|ip| format!("{ip}:{}", peer.listen_port))
                             .unwrap_or_default();
-                        self.pending_peer = Some(PendingPeerConfig::new(
-                            peer.client_config_template,
-                            peer.suggested_filename,
-                            endpoint.clone(),
-                        ));
-                        self.peer_endpoint_input = Some(endpoint);
-                        self.message = Some(Message::Success("Peer added".into()));
-                        self.refresh_tunnels();
-                    }
-                    Err(e) => self.message = Some(Message::Error(e.to_string())),
-                }
+                        (peer, endpoint)
+                    });
+                    Box::new(move |app: &mut App| match result {
+                        Ok((peer, endpoint)) => {
+                            app.pending_peer = Some(PendingPeerConfig::new(
+                                peer.client_config_template,
+                                peer.suggested_filename,
+                                endpoint.clone(),
+                            ));
+                            app.peer_endpoint_input = Some(endpoint);
+                            app.notify(Message::Success("Peer added".into()));
+                            app.refresh_tunnels();
+                        }
+                        Err(e) => app.notify(Message::Error(e.to_string())),
+                    })
+                });
             }
-            (KeyCode::Char('t'), _) => self.toggle_selected(),
-            (KeyCode::Char('e'), _) => {
+            Action::Toggle => self.toggle_selected(),
+            Action::Export => {
+                let names = self.marked_names();
+                self.end_visual();
                 if self.tunnels.is_empty() {
-                    self.message = Some(Message::Error("No tunnels to export".into()));
+                    self.notify(Message::Error("No tunnels to export".into()));
                 } else {
+                    self.export_names = (!names.is_empty()).then_some(names);
                     self.export_path = Some("wg-tunnels.zip".into());
                 }
             }
-            (KeyCode::Char('r'), _) => {
+            Action::PauseRefresh => {
+                self.refresh_paused = !self.refresh_paused;
+                let state = if self.refresh_paused {
+                    "paused"
+                } else {
+                    "resumed"
+                };
+                self.notify(Message::Info(format!("Auto-refresh {state}")));
+            }
+            Action::Refresh => {
                 self.refresh_tunnels();
-                self.message = Some(Message::Info("Refreshed".into()));
+                self.notify(Message::Info("Refreshed".into()));
             }
-            (KeyCode::Char('?'), _) => self.show_help = true,
-            _ => {}
+            Action::Log => self.log_scroll = Some(0),
+            Action::Settings => self.settings_screen = Some(SettingsScreen::default()),
+            Action::Help => self.show_help = true,
+        }
+    }
+
+    fn run_diagnostics(&mut self) {
+        let Some(tunnel) = self.selected().cloned() else {
+            return;
+        };
+        self.run_task(format!("Diagnosing '{}'", tunnel.name), move |cancel| {
+            let result = diagnose(&tunnel, cancel);
+            Box::new(move |app: &mut App| match result {
+                Ok(checks) => {
+                    let failed = checks.iter().filter(|c| !c.passed).count();
+                    app.activity.push(if failed == 0 {
+                        Message::Success(format!("Diagnostics for '{}': all passed", tunnel.name))
+                    } else {
+                        Message::Error(format!(
+                            "Diagnostics for '{}': {failed} of {} checks failed",
+                            tunnel.name,
+                            checks.len()
+                        ))
+                    });
+                    app.diagnostics = Some((tunnel.name, checks));
+                }
+                Err(e) => app.notify(Message::Error(e.to_string())),
+            })
+        });
+    }
+
+    fn run_mtu_probe(&mut self) {
+        let Some(tunnel) = self.selected().cloned() else {
+            return;
+        };
+        self.run_task(
+            format!("Probing path MTU of '{}'", tunnel.name),
+            move |cancel| {
+                let result = probe_mtu(&tunnel, cancel);
+                Box::new(move |app: &mut App| match result {
+                    Ok(probe) => {
+                        app.activity.push(Message::Info(format!(
+                            "Path MTU of '{}': {} to {}, recommended MTU {}",
+                            tunnel.name, probe.path_mtu, probe.endpoint, probe.recommended
+                        )));
+                        app.mtu_probe = Some((tunnel.name, probe));
+                    }
+                    Err(e) => app.notify(Message::Error(e.to_string())),
+                })
+            },
+        );
+    }
+
+    /// Toggles the selected group, or opens the selected tunnel in the edit
+    /// form.
+    fn open_selected(&mut self) {
+        if let Some((tag, _)) = self.selected_group() {
+            let collapsed = self.collapsed.contains(tag);
+            self.set_group_collapsed(!collapsed);
+            return;
+        }
+        if self.read_only {
+            self.show_details = true;
+            return;
+        }
+        let Some(tunnel) = self.selected() else {
+            return;
+        };
+        let was_active = tunnel.is_active;
+        match parse_tunnel_config(&tunnel.name) {
+            Ok(draft) => {
+                let form = EditFormState::new(tunnel.name.clone(), draft, was_active);
+                self.edit_form = Some(form);
+            }
+            Err(e) => self.notify(Message::Error(e.to_string())),
         }
     }
 
@@ -747,20 +1935,33 @@ impl App {
         self.render_header(frame, main[0]);
         self.render_list(frame, main[1]);
         self.render_status(frame, main[2]);
+        self.list_area = main[1];
 
+        self.details_area = None;
         if self.show_details && chunks.len() > 1 {
             self.render_details(frame, chunks[1]);
+            self.details_area = Some(chunks[1]);
         }
         if self.show_help {
-            render_help(frame);
+            render_help(frame, &self.keymap.help());
         }
-        if self.confirm_delete
-            && let Some(tunnel) = self.selected()
-        {
-            render_confirm(frame, &tunnel.name);
+        if let Some(ref names) = self.confirm_delete {
+            render_confirm(frame, names);
+        }
+        if let Some(scroll) = self.log_scroll {
+            render_activity_log(frame, self.activity.entries(), scroll);
+        }
+        if let Some((ref title, ref results)) = self.bulk_results {
+            render_bulk_results(frame, title, results);
         }
-        if let Some(ref name) = self.confirm_full_tunnel {
-            render_full_tunnel_warning(frame, name);
+        if let Some((ref name, ref checks)) = self.diagnostics {
+            render_diagnostics(frame, name, checks);
+        }
+        if let Some((ref name, ref probe)) = self.mtu_probe {
+            render_mtu_probe(frame, name, probe, !self.read_only);
+        }
+        if let Some(ref confirm) = self.confirm_up {
+            render_up_warning(frame, &confirm.conflicts, &confirm.full_tunnel);
         }
         if self.show_add_menu {
             render_add_menu(frame);
@@ -777,27 +1978,70 @@ impl App {
                 cwd.as_deref(),
             );
         }
+        if let Some(ref new_name) = self.rename_input
+            && let Some(tunnel) = self.selected()
+        {
+            let hint = tunnel
+                .is_active
+                .then_some("the tunnel will be restarted under the new name");
+            render_input(
+                frame,
+                &format!("Rename '{}'", tunnel.name),
+                "New interface name:",
+                new_name,
+                hint,
+            );
+        }
+        if let Some(ref tags) = self.tags_input
+            && let Some(tunnel) = self.selected()
+        {
+            render_input(
+                frame,
+                &format!("Tags for '{}'", tunnel.name),
+                "Tags:",
+                tags,
+                Some("comma-separated, e.g. site-a, customer-x; empty to remove"),
+            );
+        }
         if let Some(ref path) = self.export_path {
             let hint = std::env::current_dir()
                 .map(|p| {
                     format!(
                         "{} tunnel(s) — cwd: {}  (use ~/ for home)",
-                        self.tunnels.len(),
+                        self.export_names
+                            .as_ref()
+                            .map_or(self.tunnels.len(), Vec::len),
                         p.display()
                     )
                 })
                 .ok();
             render_input(
                 frame,
-                "Export All Tunnels",
+                if self.export_names.is_some() {
+                    "Export Selected Tunnels"
+                } else {
+                    "Export All Tunnels"
+                },
                 "Destination (.zip):",
                 path,
                 hint.as_deref(),
             );
         }
+        if let Some(ref screen) = self.settings_screen {
+            render_settings(
+                frame,
+                &settings(),
+                screen.selected,
+                screen.editing.as_deref(),
+                screen.status.as_ref(),
+            );
+        }
         if let Some(ref form) = self.edit_form {
             render_edit_form(frame, form);
         }
+        if let Some(ref mut editor) = self.raw_editor {
+            render_raw_editor(frame, editor);
+        }
         if let Some(ref wizard) = self.new_tunnel {
             let (title, prompt, hint) = wizard.ui();
             render_input(
@@ -837,6 +2081,15 @@ impl App {
                 render_peer_config(frame, &peer.config_text, &peer.suggested_path);
             }
         }
+        if let Some(ref excluded) = self.split_tunnel_input {
+            render_input(
+                frame,
+                "Split Tunnel",
+                "Route everything except:",
+                excluded,
+                Some("comma-separated prefixes or hosts, e.g. 192.168.0.0/16, 10.1.2.3"),
+            );
+        }
         if let Some(ref path) = self.peer_save_path {
             render_input(
                 frame,
@@ -849,59 +2102,156 @@ impl App {
     }
 
     fn render_header(&self, f: &mut Frame, area: Rect) {
-        let title = Line::from(vec![
-            " WireGuard ".fg(Color::Cyan).bold(),
-            "TUI Manager".fg(Color::White),
+        let mut title = Line::from(vec![
+            " WireGuard ".fg(theme().accent).bold(),
+            "TUI Manager".fg(theme().text),
         ]);
-        f.render_widget(Paragraph::new(title).block(bordered_block(None)), area);
+        if self.read_only {
+            title.push_span("  read-only".fg(theme().highlight));
+        }
+        let refresh = match settings().refresh_interval {
+            0 => "auto-refresh off ".to_string(),
+            _ if self.refresh_paused => "auto-refresh paused ".to_string(),
+            secs => format!("auto-refresh {secs}s "),
+        };
+        f.render_widget(
+            Paragraph::new(title)
+                .block(bordered_block(None).title_bottom(Line::from(refresh).right_aligned())),
+            area,
+        );
     }
 
     fn render_list(&mut self, f: &mut Frame, area: Rect) {
+        let grouped = matches!(self.rows.first(), Some(ListRow::Group { .. }));
+        let marked = self.marked_names();
+        let indent = if grouped { "  " } else { "" };
         let items: Vec<ListItem> = self
-            .tunnels
+            .rows
             .iter()
-            .map(|t| {
-                let (icon, color) = if t.is_active {
-                    ("●", Color::Green)
-                } else {
-                    ("○", Color::DarkGray)
-                };
-                ListItem::new(Line::from(vec![
-                    format!(" {icon} ").fg(color),
-                    t.name.clone().fg(Color::White),
-                ]))
+            .map(|row| match row {
+                ListRow::Group { tag, members } => {
+                    let arrow = if self.collapsed.contains(tag) {
+                        "▸"
+                    } else {
+                        "▾"
+                    };
+                    let up = members
+                        .iter()
+                        .filter(|&&i| self.tunnels[i].is_active)
+                        .count();
+                    ListItem::new(Line::from(vec![
+                        format!(" {arrow} {tag} ").fg(theme().accent).bold(),
+                        format!("{up}/{} up", members.len()).fg(theme().muted),
+                    ]))
+                }
+                ListRow::Tunnel(i) => {
+                    let t = &self.tunnels[*i];
+                    let (icon, color) = if t.is_active {
+                        ("●", theme().success)
+                    } else {
+                        ("○", theme().muted)
+                    };
+                    let (mark, name_color) = if marked.contains(&t.name) {
+                        ("■", theme().highlight)
+                    } else {
+                        (" ", theme().text)
+                    };
+                    ListItem::new(Line::from(vec![
+                        format!("{indent}{mark}{icon} ").fg(color),
+                        t.name.clone().fg(name_color),
+                    ]))
+                }
             })
             .collect();
 
+        let title = self.list_title();
         let list = List::new(items)
-            .block(bordered_block(Some(" Tunnels ")))
-            .highlight_style(
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD),
-            )
+            .block(bordered_block(Some(&title)))
+            .highlight_style(theme().selected())
             .highlight_symbol("▶ ");
 
         f.render_stateful_widget(list, area, &mut self.list_state);
     }
 
+    /// The list title, showing the active filter and how many tunnels match.
+    fn list_title(&self) -> String {
+        let mut title = format!(" Tunnels ↓{} ", self.state.sort.label());
+        if let Some(label) = self.filter.quick.label() {
+            title.push_str(&format!("[{label}] "));
+        }
+        if self.searching || !self.filter.query.is_empty() {
+            let cursor = if self.searching { "█" } else { "" };
+            title.push_str(&format!("/{}{cursor} ", self.filter.query));
+        }
+        if !self.filter.is_empty() {
+            title.push_str(&format!("({}/{}) ", self.match_count, self.tunnels.len()));
+        }
+        if self.visual_anchor.is_some() {
+            title.push_str("-- VISUAL -- ");
+        }
+        let marked = self.marked_names().len();
+        if marked > 0 {
+            title.push_str(&format!("[{marked} selected] "));
+        }
+        title
+    }
+
     fn render_status(&self, f: &mut Frame, area: Rect) {
         let content = match &self.message {
-            Some(msg) => Line::styled(format!(" {}", msg.text()), msg.style()),
-            None => Line::from(vec![
-                " j/k".fg(Color::Yellow),
-                " nav  ".into(),
-                "Enter".fg(Color::Yellow),
-                " edit  ".into(),
-                "t".fg(Color::Yellow),
-                " toggle  ".into(),
-                "d".fg(Color::Yellow),
-                " details  ".into(),
-                "?".fg(Color::Yellow),
-                " help  ".into(),
-                "q".fg(Color::Yellow),
-                " quit".into(),
+            Some(msg) => {
+                // Multi-line errors such as wg-quick output are shown in full
+                // in the activity log.
+                let text = msg.text();
+                let first = text.lines().next().unwrap_or_default();
+                let more = if first.len() < text.trim_end().len() {
+                    " …"
+                } else {
+                    ""
+                };
+                Line::styled(format!(" {first}{more}"), msg.style())
+            }
+            None if self.task.is_some() => {
+                let task = self.task.as_ref().expect("task is running");
+                let spinner = format!(" {} {}  ", task.spinner(), task.label).fg(theme().info);
+                if task.is_cancelled() {
+                    Line::from(vec![spinner, "cancelling…".fg(theme().muted)])
+                } else {
+                    Line::from(vec![spinner, "Esc".fg(theme().highlight), " cancel".into()])
+                }
+            }
+            None if self.searching => Line::from(vec![
+                " Search names, endpoints and peers  ".into(),
+                "Enter".fg(theme().highlight),
+                " keep filter  ".into(),
+                "Esc".fg(theme().highlight),
+                " clear".into(),
             ]),
+            None => {
+                let keys = &self.keymap;
+                let nav = format!(
+                    "{}/{}",
+                    keys.short_label(Action::Down),
+                    keys.short_label(Action::Up)
+                );
+                let hints = [
+                    (nav, "nav"),
+                    (
+                        keys.short_label(Action::Edit),
+                        if self.read_only { "open" } else { "edit" },
+                    ),
+                    (keys.short_label(Action::Toggle), "toggle"),
+                    (keys.short_label(Action::Details), "details"),
+                    (keys.short_label(Action::Help), "help"),
+                    (keys.short_label(Action::Quit), "quit"),
+                ];
+                let mut spans = vec![" ".into()];
+                // Unbound actions, such as toggle in read-only mode, are left out.
+                for (key, hint) in hints.into_iter().filter(|(key, _)| key != "-") {
+                    spans.push(key.fg(theme().highlight));
+                    spans.push(format!(" {hint}  ").into());
+                }
+                Line::from(spans)
+            }
         };
         f.render_widget(Paragraph::new(content).block(bordered_block(None)), area);
     }
@@ -910,7 +2260,7 @@ impl App {
         let Some(tunnel) = self.selected() else {
             f.render_widget(
                 Paragraph::new(" No tunnel selected")
-                    .fg(Color::DarkGray)
+                    .fg(theme().muted)
                     .block(bordered_block(Some(" Details "))),
                 area,
             );
@@ -921,15 +2271,32 @@ impl App {
             label("Name: ", &tunnel.name),
             label("Config: ", &tunnel.config_path.display().to_string()),
             Line::from(vec![
-                "Status: ".fg(Color::Yellow),
+                "Status: ".fg(theme().highlight),
                 if tunnel.is_active {
-                    "Active".fg(Color::Green)
+                    "Active".fg(theme().success)
+                } else {
+                    "Inactive".fg(theme().error)
+                },
+            ]),
+            Line::from(vec![
+                "Kill Switch: ".fg(theme().highlight),
+                if tunnel.kill_switch {
+                    "On".fg(theme().success)
                 } else {
-                    "Inactive".fg(Color::Red)
+                    "Off".fg(theme().muted)
                 },
             ]),
-            Line::raw(""),
         ];
+        if !tunnel.tags.is_empty() {
+            lines.push(label("Tags: ", &tunnel.tags.join(", ")));
+        }
+        lines.push(Line::raw(""));
+
+        if !tunnel.lints.is_empty() {
+            lines.push(section(&format!("Warnings ({})", tunnel.lints.len())));
+            lines.extend(tunnel.lints.iter().map(lint_line));
+            lines.push(Line::raw(""));
+        }
 
         if let Some(iface) = &tunnel.interface {
             lines.push(section("Interface"));
@@ -949,10 +2316,14 @@ impl App {
             }
         }
 
+        let scroll = self
+            .details_scroll
+            .min(lines.len().saturating_sub(1) as u16);
         f.render_widget(
             Paragraph::new(Text::from(lines))
                 .block(bordered_block(Some(" Details ")))
-                .wrap(Wrap { trim: false }),
+                .wrap(Wrap { trim: false })
+                .scroll((scroll, 0)),
             area,
         );
     }
@@ -998,6 +2369,7 @@ impl PendingPeerConfig {
 enum NewTunnelWizard {
     Client(NewClientWizard),
     Server(NewServerWizard),
+    Clone(CloneWizard),
 }
 
 impl NewTunnelWizard {
@@ -1025,6 +2397,7 @@ impl NewTunnelWizard {
         match self {
             Self::Client(wizard) => wizard.current_value(),
             Self::Server(wizard) => wizard.current_value(),
+            Self::Clone(wizard) => wizard.current_value(),
         }
     }
 
@@ -1032,6 +2405,7 @@ impl NewTunnelWizard {
         match self {
             Self::Client(wizard) => wizard.current_value_mut(),
             Self::Server(wizard) => wizard.current_value_mut(),
+            Self::Clone(wizard) => wizard.current_value_mut(),
         }
     }
 
@@ -1039,13 +2413,31 @@ impl NewTunnelWizard {
         match self {
             Self::Client(wizard) => wizard.ui(),
             Self::Server(wizard) => wizard.ui(),
+            Self::Clone(wizard) => wizard.ui(),
         }
     }
 
+    fn clone_of(source: String, name: String, listen_port: String) -> Self {
+        Self::Clone(CloneWizard {
+            step: CloneWizardStep::Name,
+            draft: CloneTunnelDraft {
+                source,
+                name,
+                fresh_keys: "y".into(),
+                listen_port,
+            },
+        })
+    }
+
+    fn edits_allowed_ips(&self) -> bool {
+        matches!(self, Self::Client(w) if w.step == ClientWizardStep::AllowedIps)
+    }
+
     fn validate_current(&self) -> Option<String> {
         match self {
             Self::Client(wizard) => wizard.validate_current(),
             Self::Server(wizard) => wizard.validate_current(),
+            Self::Clone(wizard) => wizard.validate_current(),
         }
     }
 
@@ -1053,6 +2445,7 @@ impl NewTunnelWizard {
         match self {
             Self::Client(wizard) => wizard.advance(),
             Self::Server(wizard) => wizard.advance(),
+            Self::Clone(wizard) => wizard.advance(),
         }
     }
 }
@@ -1066,6 +2459,7 @@ enum ClientWizardStep {
     PeerPublicKey,
     AllowedIps,
     Endpoint,
+    KillSwitch,
 }
 
 impl ClientWizardStep {
@@ -1077,7 +2471,8 @@ impl ClientWizardStep {
             Self::Dns => Some(Self::PeerPublicKey),
             Self::PeerPublicKey => Some(Self::AllowedIps),
             Self::AllowedIps => Some(Self::Endpoint),
-            Self::Endpoint => None,
+            Self::Endpoint => Some(Self::KillSwitch),
+            Self::KillSwitch => None,
         }
     }
 
@@ -1090,6 +2485,7 @@ impl ClientWizardStep {
             Self::PeerPublicKey => 5,
             Self::AllowedIps => 6,
             Self::Endpoint => 7,
+            Self::KillSwitch => 8,
         }
     }
 }
@@ -1108,10 +2504,11 @@ impl NewClientWizard {
                 name,
                 private_key: String::new(),
                 address: "10.0.0.2/32".into(),
-                dns: String::new(),
+                dns: settings().dns,
                 peer_public_key: String::new(),
                 allowed_ips: "0.0.0.0/0, ::/0".into(),
                 endpoint: String::new(),
+                kill_switch: "n".into(),
             },
         }
     }
@@ -1125,6 +2522,7 @@ impl NewClientWizard {
             ClientWizardStep::PeerPublicKey => &self.draft.peer_public_key,
             ClientWizardStep::AllowedIps => &self.draft.allowed_ips,
             ClientWizardStep::Endpoint => &self.draft.endpoint,
+            ClientWizardStep::KillSwitch => &self.draft.kill_switch,
         }
     }
 
@@ -1137,11 +2535,12 @@ impl NewClientWizard {
             ClientWizardStep::PeerPublicKey => &mut self.draft.peer_public_key,
             ClientWizardStep::AllowedIps => &mut self.draft.allowed_ips,
             ClientWizardStep::Endpoint => &mut self.draft.endpoint,
+            ClientWizardStep::KillSwitch => &mut self.draft.kill_switch,
         }
     }
 
     fn ui(&self) -> (String, &'static str, Option<String>) {
-        let title = format!("New Tunnel (Client {}/7)", self.step.index());
+        let title = format!("New Tunnel (Client {}/8)", self.step.index());
         let (prompt, hint) = match self.step {
             ClientWizardStep::Name => ("Interface name:", Some("required".into())),
             ClientWizardStep::PrivateKey => ("Private key:", Some("required".into())),
@@ -1150,10 +2549,19 @@ impl NewClientWizard {
             }
             ClientWizardStep::Dns => ("DNS (optional):", Some("comma-separated".into())),
             ClientWizardStep::PeerPublicKey => ("Peer public key:", Some("required".into())),
-            ClientWizardStep::AllowedIps => {
-                ("Peer allowed IPs:", Some("default: 0.0.0.0/0, ::/0".into()))
-            }
+            ClientWizardStep::AllowedIps => (
+                "Peer allowed IPs:",
+                Some("default: 0.0.0.0/0, ::/0  (Ctrl+X: route everything except…)".into()),
+            ),
             ClientWizardStep::Endpoint => ("Peer endpoint:", Some("host:port".into())),
+            ClientWizardStep::KillSwitch => (
+                "Kill switch (y/n):",
+                Some(
+                    "block traffic outside the tunnel except to the endpoint and LAN; \
+                     a host name is pinned to its current IPs"
+                        .into(),
+                ),
+            ),
         };
         (title, prompt, hint)
     }
@@ -1179,6 +2587,115 @@ impl NewClientWizard {
                 }
             }
             ClientWizardStep::Dns => {}
+            ClientWizardStep::KillSwitch => {
+                if !matches!(
+                    value.to_ascii_lowercase().as_str(),
+                    "" | "y" | "yes" | "n" | "no"
+                ) {
+                    return Some("Answer y or n".into());
+                }
+            }
+        }
+        None
+    }
+
+    fn advance(&mut self) -> bool {
+        if let Some(next) = self.step.next() {
+            self.step = next;
+            false
+        } else {
+            true
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CloneWizardStep {
+    Name,
+    FreshKeys,
+    ListenPort,
+}
+
+impl CloneWizardStep {
+    fn next(self) -> Option<Self> {
+        match self {
+            Self::Name => Some(Self::FreshKeys),
+            Self::FreshKeys => Some(Self::ListenPort),
+            Self::ListenPort => None,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Self::Name => 1,
+            Self::FreshKeys => 2,
+            Self::ListenPort => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CloneWizard {
+    step: CloneWizardStep,
+    draft: CloneTunnelDraft,
+}
+
+impl CloneWizard {
+    fn current_value(&self) -> &str {
+        match self.step {
+            CloneWizardStep::Name => &self.draft.name,
+            CloneWizardStep::FreshKeys => &self.draft.fresh_keys,
+            CloneWizardStep::ListenPort => &self.draft.listen_port,
+        }
+    }
+
+    fn current_value_mut(&mut self) -> &mut String {
+        match self.step {
+            CloneWizardStep::Name => &mut self.draft.name,
+            CloneWizardStep::FreshKeys => &mut self.draft.fresh_keys,
+            CloneWizardStep::ListenPort => &mut self.draft.listen_port,
+        }
+    }
+
+    fn ui(&self) -> (String, &'static str, Option<String>) {
+        let title = format!("Clone '{}' ({}/3)", self.draft.source, self.step.index());
+        let (prompt, hint) = match self.step {
+            CloneWizardStep::Name => ("New interface name:", Some("required".into())),
+            CloneWizardStep::FreshKeys => (
+                "Generate a new keypair (y/n):",
+                Some("peers must be given the new public key".into()),
+            ),
+            CloneWizardStep::ListenPort => {
+                ("Listen port:", Some("leave empty for no ListenPort".into()))
+            }
+        };
+        (title, prompt, hint)
+    }
+
+    fn validate_current(&self) -> Option<String> {
+        let value = self.current_value().trim();
+        match self.step {
+            CloneWizardStep::Name => {
+                if value.is_empty() {
+                    return Some("Interface name is required".into());
+                }
+                if value.chars().any(|c| c.is_whitespace() || c == '/') {
+                    return Some("Interface name cannot contain spaces or '/'".into());
+                }
+            }
+            CloneWizardStep::FreshKeys => {
+                if !matches!(
+                    value.to_ascii_lowercase().as_str(),
+                    "" | "y" | "yes" | "n" | "no"
+                ) {
+                    return Some("Answer y or n".into());
+                }
+            }
+            CloneWizardStep::ListenPort => {
+                if !value.is_empty() && value.parse::<u16>().is_err() {
+                    return Some("Listen port must be a valid number".into());
+                }
+            }
         }
         None
     }
@@ -1270,7 +2787,10 @@ impl NewServerWizard {
         let (prompt, hint) = match self.step {
             ServerWizardStep::Name => ("Interface name:", Some("required".into())),
             ServerWizardStep::Address => ("Server address:", Some("example: 10.0.0.1/32".into())),
-            ServerWizardStep::ListenPort => ("Listen port:", Some("default: 51820".into())),
+            ServerWizardStep::ListenPort => (
+                "Listen port:",
+                Some(format!("default: {}", settings().listen_port)),
+            ),
             ServerWizardStep::EgressInterface => {
                 let hint = if self.draft.egress_interface.is_empty() {
                     "required".into()
@@ -1315,55 +2835,205 @@ impl NewServerWizard {
     }
 }
 
+/// Lines moved by PageUp/PageDown in the raw editor.
+const RAW_EDITOR_PAGE: usize = 20;
+
+/// Full-screen editor for a tunnel's `.conf` text.
+#[derive(Debug, Clone)]
+pub struct RawEditorState {
+    pub tunnel_name: String,
+    pub was_active: bool,
+    pub area: TextArea,
+    original: String,
+    /// Shows PrivateKey and PresharedKey values instead of masking them.
+    pub reveal_keys: bool,
+    /// First visible line and column, kept up to date while rendering.
+    pub scroll: (usize, usize),
+    pub status: Option<Message>,
+    confirm_discard: bool,
+}
+
+impl RawEditorState {
+    fn new(name: String, content: &str, was_active: bool) -> Self {
+        let area = TextArea::new(content);
+        Self {
+            tunnel_name: name,
+            was_active,
+            original: area.text(),
+            area,
+            reveal_keys: false,
+            scroll: (0, 0),
+            status: None,
+            confirm_discard: false,
+        }
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.area.text() != self.original
+    }
+}
+
+/// The kill switch toggle sits between the interface and peer fields.
+pub const KILL_SWITCH_FIELD: usize = EDIT_INTERFACE_KEYS.len();
+pub const PEER_FIELD_START: usize = KILL_SWITCH_FIELD + 1;
+/// AllowedIPs is the fourth of [`EDIT_PEER_KEYS`].
+const ALLOWED_IPS_FIELD: usize = PEER_FIELD_START + 3;
+
+/// Focus moves through the interface inputs, the kill switch toggle, then
+/// the inputs of the peer at `peer_index`.
 #[derive(Debug, Clone)]
 pub struct EditFormState {
-    pub inputs: Vec<Input>,
+    pub interface: Vec<Input>,
+    pub peers: Vec<PeerForm>,
+    pub peer_index: usize,
     pub focused_field: usize,
     pub tunnel_name: String,
     pub was_active: bool,
+    pub kill_switch: bool,
+    /// Field named by the last failed save, drawn in the error color until
+    /// edited.
+    pub invalid_field: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PeerForm {
+    pub inputs: Vec<Input>,
+    source: Option<usize>,
+}
+
+impl PeerForm {
+    fn new(draft: PeerDraft) -> Self {
+        Self {
+            inputs: draft.values.into_iter().map(Input::new).collect(),
+            source: draft.source,
+        }
+    }
 }
 
 impl EditFormState {
     fn new(name: String, draft: EditTunnelDraft, was_active: bool) -> Self {
-        let inputs = vec![
-            Input::new(draft.address),
-            Input::new(draft.dns),
-            Input::new(draft.listen_port),
-            Input::new(draft.mtu),
-            Input::new(draft.peer_endpoint),
-            Input::new(draft.peer_allowed_ips),
-            Input::new(draft.peer_persistent_keepalive),
-        ];
         Self {
-            inputs,
+            interface: draft.interface.into_iter().map(Input::new).collect(),
+            peers: draft.peers.into_iter().map(PeerForm::new).collect(),
+            peer_index: 0,
             focused_field: 0,
             tunnel_name: name,
             was_active,
+            kill_switch: draft.kill_switch,
+            invalid_field: None,
+        }
+    }
+
+    /// Focuses and marks the field named by [`Error::field`], such as `MTU` or
+    /// `Peer 2 Endpoint`.
+    fn mark_invalid(&mut self, field: &str) {
+        let (peer, key) = match field.strip_prefix("Peer ").and_then(|f| f.split_once(' ')) {
+            Some((n, key)) => (n.parse::<usize>().ok().and_then(|n| n.checked_sub(1)), key),
+            None => (None, field),
+        };
+        let index = match peer {
+            Some(peer) if peer < self.peers.len() => {
+                self.peer_index = peer;
+                EDIT_PEER_KEYS
+                    .iter()
+                    .position(|k| *k == key)
+                    .map(|i| PEER_FIELD_START + i)
+            }
+            _ => EDIT_INTERFACE_KEYS.iter().position(|k| *k == key),
+        };
+        if let Some(index) = index {
+            self.focused_field = index;
+            self.invalid_field = Some(index);
+        }
+    }
+
+    fn field_count(&self) -> usize {
+        if self.peers.is_empty() {
+            PEER_FIELD_START
+        } else {
+            PEER_FIELD_START + EDIT_PEER_KEYS.len()
         }
     }
 
     fn next_field(&mut self) {
-        self.focused_field = (self.focused_field + 1) % 7;
+        self.focused_field = (self.focused_field + 1) % self.field_count();
     }
 
     fn prev_field(&mut self) {
         self.focused_field = if self.focused_field == 0 {
-            6
+            self.field_count() - 1
         } else {
             self.focused_field - 1
         };
     }
 
+    pub fn focused_input(&self) -> Option<&Input> {
+        match self.focused_field {
+            f if f < KILL_SWITCH_FIELD => self.interface.get(f),
+            KILL_SWITCH_FIELD => None,
+            f => self
+                .peers
+                .get(self.peer_index)?
+                .inputs
+                .get(f - PEER_FIELD_START),
+        }
+    }
+
+    fn focused_input_mut(&mut self) -> Option<&mut Input> {
+        match self.focused_field {
+            f if f < KILL_SWITCH_FIELD => self.interface.get_mut(f),
+            KILL_SWITCH_FIELD => None,
+            f => self
+                .peers
+                .get_mut(self.peer_index)?
+                .inputs
+                .get_mut(f - PEER_FIELD_START),
+        }
+    }
+
+    fn select_peer(&mut self, delta: isize) {
+        if self.peers.is_empty() {
+            return;
+        }
+        let last = self.peers.len() as isize - 1;
+        self.peer_index = (self.peer_index as isize + delta).clamp(0, last) as usize;
+        self.invalid_field = None;
+    }
+
+    fn add_peer(&mut self) {
+        self.peers.push(PeerForm::new(PeerDraft::new()));
+        self.peer_index = self.peers.len() - 1;
+        self.focused_field = PEER_FIELD_START;
+    }
+
+    fn delete_peer(&mut self) {
+        if self.peer_index >= self.peers.len() {
+            return;
+        }
+        self.peers.remove(self.peer_index);
+        self.peer_index = self.peer_index.min(self.peers.len().saturating_sub(1));
+        if self.focused_field >= self.field_count() {
+            self.focused_field = KILL_SWITCH_FIELD;
+        }
+    }
+
     fn to_draft(&self) -> EditTunnelDraft {
         EditTunnelDraft {
             name: self.tunnel_name.clone(),
-            address: self.inputs[0].value().to_string(),
-            dns: self.inputs[1].value().to_string(),
-            listen_port: self.inputs[2].value().to_string(),
-            mtu: self.inputs[3].value().to_string(),
-            peer_endpoint: self.inputs[4].value().to_string(),
-            peer_allowed_ips: self.inputs[5].value().to_string(),
-            peer_persistent_keepalive: self.inputs[6].value().to_string(),
+            interface: self
+                .interface
+                .iter()
+                .map(|i| i.value().to_string())
+                .collect(),
+            peers: self
+                .peers
+                .iter()
+                .map(|p| PeerDraft {
+                    values: p.inputs.iter().map(|i| i.value().to_string()).collect(),
+                    source: p.source,
+                })
+                .collect(),
+            kill_switch: self.kill_switch,
         }
     }
 }
@@ -0,0 +1,301 @@
+use std::{fmt, net::IpAddr, str::FromStr};
+
+/// An IP prefix such as `10.0.0.0/24` or `fd00::1/128`.
+///
+/// The address is kept as written; comparisons use the masked network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn new(addr: IpAddr, prefix: u8) -> Option<Self> {
+        (prefix <= max_prefix(addr)).then_some(Self { addr, prefix })
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn is_ipv4(&self) -> bool {
+        self.addr.is_ipv4()
+    }
+
+    /// True for `0.0.0.0/0` and `::/0`.
+    pub fn is_default(&self) -> bool {
+        self.prefix == 0
+    }
+
+    /// True for single-address prefixes (`/32` or `/128`).
+    pub fn is_host(&self) -> bool {
+        self.prefix == max_prefix(self.addr)
+    }
+
+    pub fn network(&self) -> Self {
+        Self {
+            addr: from_bits(self.addr, to_bits(self.addr) & self.mask()),
+            prefix: self.prefix,
+        }
+    }
+
+    /// The lowest usable address: the one after the network address, or the
+    /// address itself for host and point-to-point prefixes.
+    pub fn first_host(&self) -> IpAddr {
+        if max_prefix(self.addr) - self.prefix < 2 {
+            return self.addr;
+        }
+        from_bits(self.addr, to_bits(self.network().addr) + 1)
+    }
+
+    /// True if every address of `other` is also in `self`.
+    pub fn contains(&self, other: &Self) -> bool {
+        self.is_ipv4() == other.is_ipv4()
+            && self.prefix <= other.prefix
+            && to_bits(self.addr) & self.mask() == to_bits(other.addr) & self.mask()
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.contains(other) || other.contains(self)
+    }
+
+    /// Splits a network into its two halves. Must not be a host prefix.
+    fn halves(&self) -> (Self, Self) {
+        let net = self.network();
+        let prefix = net.prefix + 1;
+        let bit = 1u128 << (max_prefix(net.addr) - prefix);
+        let high = from_bits(net.addr, to_bits(net.addr) | bit);
+        (
+            Self {
+                addr: net.addr,
+                prefix,
+            },
+            Self { addr: high, prefix },
+        )
+    }
+
+    fn mask(&self) -> u128 {
+        let width = max_prefix(self.addr);
+        if self.prefix == 0 {
+            0
+        } else {
+            (u128::MAX << (width - self.prefix)) & (u128::MAX >> (128 - u32::from(width)))
+        }
+    }
+}
+
+/// Parses a comma-separated prefix list, e.g. `"192.168.0.0/16, 10.1.2.3"`.
+pub fn parse_list(value: &str) -> Result<Vec<Cidr>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Computes the AllowedIPs that route `base` except `excluded`, as the
+/// smallest set of prefixes. An empty `base` means everything (IPv4 and IPv6).
+pub fn split_tunnel(base: &str, excluded: &str) -> Result<String, String> {
+    let mut base = parse_list(base)?;
+    if base.is_empty() {
+        base = vec!["0.0.0.0/0".parse()?, "::/0".parse()?];
+    }
+    let excluded = parse_list(excluded)?;
+    if excluded.is_empty() {
+        return Err("Nothing to exclude".into());
+    }
+    Ok(exclude(&base, &excluded)
+        .iter()
+        .map(Cidr::to_string)
+        .collect::<Vec<_>>()
+        .join(", "))
+}
+
+/// Removes every address in `excluded` from `base` and returns the minimal
+/// list of prefixes covering what is left, sorted IPv4 first.
+pub fn exclude(base: &[Cidr], excluded: &[Cidr]) -> Vec<Cidr> {
+    let mut result: Vec<Cidr> = base.iter().map(Cidr::network).collect();
+    for ex in excluded {
+        result = result.into_iter().flat_map(|c| subtract(c, ex)).collect();
+    }
+    aggregate(result)
+}
+
+fn subtract(net: Cidr, ex: &Cidr) -> Vec<Cidr> {
+    if !net.overlaps(ex) {
+        return vec![net];
+    }
+    if ex.contains(&net) {
+        return vec![];
+    }
+    let (low, high) = net.halves();
+    let mut out = subtract(low, ex);
+    out.extend(subtract(high, ex));
+    out
+}
+
+/// Sorts, drops prefixes covered by others and merges sibling halves.
+fn aggregate(mut cidrs: Vec<Cidr>) -> Vec<Cidr> {
+    loop {
+        cidrs.sort_by_key(|c| (!c.is_ipv4(), to_bits(c.addr), c.prefix));
+        cidrs.dedup();
+        let mut merged: Vec<Cidr> = Vec::with_capacity(cidrs.len());
+        let mut changed = false;
+        for c in cidrs {
+            match merged.last() {
+                Some(last) if last.contains(&c) => changed = true,
+                Some(last) if last.prefix == c.prefix && last.prefix > 0 => {
+                    let parent = Cidr {
+                        addr: last.addr,
+                        prefix: last.prefix - 1,
+                    }
+                    .network();
+                    if parent.halves() == (*last, c) {
+                        merged.pop();
+                        merged.push(parent);
+                        changed = true;
+                    } else {
+                        merged.push(c);
+                    }
+                }
+                _ => merged.push(c),
+            }
+        }
+        cidrs = merged;
+        if !changed {
+            return cidrs;
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{s}' is not a valid IP address"))?;
+        let prefix = match prefix {
+            Some(p) => p
+                .trim()
+                .parse()
+                .map_err(|_| format!("'{s}' has an invalid prefix length"))?,
+            None => max_prefix(addr),
+        };
+        Self::new(addr, prefix).ok_or_else(|| format!("'{s}' has an invalid prefix length"))
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+fn max_prefix(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn to_bits(addr: IpAddr) -> u128 {
+    match addr {
+        IpAddr::V4(a) => u128::from(u32::from(a)),
+        IpAddr::V6(a) => u128::from(a),
+    }
+}
+
+fn from_bits(like: IpAddr, bits: u128) -> IpAddr {
+    match like {
+        IpAddr::V4(_) => IpAddr::V4((bits as u32).into()),
+        IpAddr::V6(_) => IpAddr::V6(bits.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excludes_lan_from_default_route() {
+        assert_eq!(
+            split_tunnel("0.0.0.0/0", "192.168.0.0/16").unwrap(),
+            "0.0.0.0/1, 128.0.0.0/2, 192.0.0.0/9, 192.128.0.0/11, 192.160.0.0/13, \
+             192.169.0.0/16, 192.170.0.0/15, 192.172.0.0/14, 192.176.0.0/12, 192.192.0.0/10, \
+             193.0.0.0/8, 194.0.0.0/7, 196.0.0.0/6, 200.0.0.0/5, 208.0.0.0/4, 224.0.0.0/3"
+        );
+    }
+
+    #[test]
+    fn excludes_single_host() {
+        let result = exclude(
+            &parse_list("10.1.2.0/30").unwrap(),
+            &parse_list("10.1.2.3").unwrap(),
+        );
+        assert_eq!(result, parse_list("10.1.2.0/31, 10.1.2.2/32").unwrap());
+    }
+
+    #[test]
+    fn empty_base_covers_both_families() {
+        let result = split_tunnel("", "10.0.0.0/8, fc00::/7").unwrap();
+        let cidrs = parse_list(&result).unwrap();
+        assert!(cidrs.iter().any(|c| !c.is_ipv4()));
+        assert!(
+            !cidrs
+                .iter()
+                .any(|c| c.overlaps(&"10.1.2.3".parse().unwrap()))
+        );
+        assert!(
+            !cidrs
+                .iter()
+                .any(|c| c.overlaps(&"fd00::1".parse().unwrap()))
+        );
+        assert!(
+            cidrs
+                .iter()
+                .any(|c| c.contains(&"2001:db8::1".parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn aggregates_adjacent_halves() {
+        let result = exclude(
+            &parse_list("0.0.0.0/1, 128.0.0.0/1").unwrap(),
+            &parse_list("::1").unwrap(),
+        );
+        assert_eq!(result, parse_list("0.0.0.0/0").unwrap());
+    }
+
+    #[test]
+    fn excluding_everything_leaves_nothing() {
+        assert!(
+            exclude(
+                &parse_list("10.0.0.0/24").unwrap(),
+                &parse_list("10.0.0.0/8").unwrap()
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn finds_first_host() {
+        let host = |s: &str| s.parse::<Cidr>().unwrap().first_host().to_string();
+        assert_eq!(host("10.0.0.7/24"), "10.0.0.1");
+        assert_eq!(host("10.0.0.7/32"), "10.0.0.7");
+        assert_eq!(host("fd00::5/64"), "fd00::1");
+    }
+
+    #[test]
+    fn rejects_invalid_prefixes() {
+        assert!(split_tunnel("0.0.0.0/0", "10.0.0.0/33").is_err());
+        assert!(split_tunnel("0.0.0.0/0", "not-an-ip").is_err());
+    }
+}
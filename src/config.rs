@@ -0,0 +1,278 @@
+/// Interface keys understood by `wg-quick` but not by `wg setconf`.
+const WG_QUICK_ONLY_KEYS: &[&str] = &[
+    "Address",
+    "DNS",
+    "MTU",
+    "Table",
+    "PreUp",
+    "PreDown",
+    "PostUp",
+    "PostDown",
+    "SaveConfig",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    Interface,
+    Peer,
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub kind: SectionKind,
+    pub header: String,
+    pub entries: Vec<Entry>,
+}
+
+impl Section {
+    fn new(header: &str) -> Self {
+        let kind = if header.eq_ignore_ascii_case("[Interface]") {
+            SectionKind::Interface
+        } else if header.eq_ignore_ascii_case("[Peer]") {
+            SectionKind::Peer
+        } else {
+            SectionKind::Other
+        };
+        Self {
+            kind,
+            header: header.to_string(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns the first value for `key`, compared case-insensitively.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|e| e.key.eq_ignore_ascii_case(key))
+            .map(|e| e.value.as_str())
+    }
+
+    /// Returns every value for `key`, in file order.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.entries
+            .iter()
+            .filter(move |e| e.key.eq_ignore_ascii_case(key))
+            .map(|e| e.value.as_str())
+    }
+
+    /// Returns the comma-separated values of every `key` line, flattened.
+    pub fn get_list(&self, key: &str) -> Vec<String> {
+        self.get_all(key)
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// A parsed `wg-quick` configuration.
+///
+/// Comments and blank lines are dropped; keys keep their original spelling
+/// but are looked up case-insensitively.
+#[derive(Debug, Clone, Default)]
+pub struct WgConfig {
+    pub sections: Vec<Section>,
+}
+
+impl WgConfig {
+    pub fn parse(content: &str) -> Self {
+        let mut sections: Vec<Section> = Vec::new();
+
+        for line in content.lines() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                sections.push(Section::new(line));
+                continue;
+            }
+            let Some(section) = sections.last_mut() else {
+                continue;
+            };
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            section.entries.push(Entry {
+                key: key.trim().to_string(),
+                value: value.trim().to_string(),
+            });
+        }
+
+        Self { sections }
+    }
+
+    pub fn interface(&self) -> Option<&Section> {
+        self.sections
+            .iter()
+            .find(|s| s.kind == SectionKind::Interface)
+    }
+
+    pub fn peers(&self) -> impl Iterator<Item = &Section> {
+        self.sections.iter().filter(|s| s.kind == SectionKind::Peer)
+    }
+
+    /// Renders the config in the form accepted by `wg setconf` / `wg syncconf`,
+    /// like `wg-quick strip`, except that comments are dropped and keys are
+    /// matched case-insensitively.
+    pub fn strip(&self) -> String {
+        let mut out = String::new();
+        for section in &self.sections {
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&section.header);
+            out.push('\n');
+            for entry in &section.entries {
+                if section.kind == SectionKind::Interface && is_wg_quick_only_key(&entry.key) {
+                    continue;
+                }
+                out.push_str(&format!("{} = {}\n", entry.key, entry.value));
+            }
+        }
+        out
+    }
+}
+
+/// Collects peer names from `# Name = ...` comments inside `[Peer]` sections,
+/// a convention several WireGuard tools use.
+pub fn peer_names(content: &str) -> Vec<String> {
+    let mut in_peer = false;
+    let mut names = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_peer = line.eq_ignore_ascii_case("[Peer]");
+        } else if in_peer
+            && let Some(comment) = line.strip_prefix('#')
+            && let Some((key, value)) = comment.split_once('=')
+            && key.trim().eq_ignore_ascii_case("Name")
+        {
+            names.push(value.trim().to_string());
+        }
+    }
+    names
+}
+
+/// Reads tags from a `# Tags = a, b` comment above the first section.
+pub fn tags(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .take_while(|line| !line.starts_with('['))
+        .find_map(tags_comment)
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Replaces the leading `# Tags = ...` comment of `content`, adding it at the
+/// top if missing and removing it when `tags` is empty.
+pub fn set_tags(content: &str, tags: &[String]) -> String {
+    let mut lines: Vec<&str> = content.lines().collect();
+    let header = lines.iter().position(|l| l.trim().starts_with('['));
+    let existing = lines[..header.unwrap_or(lines.len())]
+        .iter()
+        .position(|l| tags_comment(l.trim()).is_some());
+    let line = format!("# Tags = {}", tags.join(", "));
+    match (existing, tags.is_empty()) {
+        (Some(i), true) => {
+            lines.remove(i);
+        }
+        (Some(i), false) => lines[i] = &line,
+        (None, false) => lines.insert(0, &line),
+        (None, true) => {}
+    }
+    let mut result = lines.join("\n");
+    result.push('\n');
+    result
+}
+
+fn tags_comment(line: &str) -> Option<&str> {
+    let (key, value) = line.strip_prefix('#')?.split_once('=')?;
+    key.trim().eq_ignore_ascii_case("Tags").then_some(value)
+}
+
+fn is_wg_quick_only_key(key: &str) -> bool {
+    WG_QUICK_ONLY_KEYS
+        .iter()
+        .any(|k| k.eq_ignore_ascii_case(key))
+}
+
+/// Drops everything from the first `#`, matching `wg-quick`. Lines starting
+/// with `;` are treated as comments as well.
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with(';') {
+        return "";
+    }
+    line.split_once('#').map_or(line, |(before, _)| before)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_server_drops_wg_quick_keys() {
+        let config = WgConfig::parse(include_str!("../tests/fixtures/server.conf"));
+        assert_eq!(
+            config.strip(),
+            include_str!("../tests/fixtures/server.stripped")
+        );
+    }
+
+    #[test]
+    fn strip_client_drops_wg_quick_keys() {
+        let config = WgConfig::parse(include_str!("../tests/fixtures/client.conf"));
+        assert_eq!(
+            config.strip(),
+            include_str!("../tests/fixtures/client.stripped")
+        );
+    }
+
+    #[test]
+    fn parse_tracks_sections() {
+        let config = WgConfig::parse(include_str!("../tests/fixtures/server.conf"));
+        let interface = config.interface().unwrap();
+        assert_eq!(interface.get("listenport"), Some("51820"));
+        assert_eq!(config.peers().count(), 2);
+
+        let peer = config.peers().nth(1).unwrap();
+        assert_eq!(peer.get_list("AllowedIPs"), ["10.0.0.3/32", "fd00::3/128"]);
+        assert_eq!(peer.get("PersistentKeepalive"), Some("25"));
+    }
+
+    #[test]
+    fn updates_tags() {
+        let content = include_str!("../tests/fixtures/server.conf");
+        assert!(tags(content).is_empty());
+
+        let tagged = set_tags(content, &["hq".into(), "prod".into()]);
+        assert!(tagged.starts_with("# Tags = hq, prod\n# Site gateway\n"));
+        assert_eq!(tags(&tagged), ["hq", "prod"]);
+
+        let retagged = set_tags(&tagged, &["lab".into()]);
+        assert_eq!(tags(&retagged), ["lab"]);
+        assert_eq!(set_tags(&retagged, &[]), content);
+    }
+
+    #[test]
+    fn reads_peer_names() {
+        let names = peer_names(include_str!("../tests/fixtures/server.conf"));
+        assert_eq!(names, ["laptop"]);
+    }
+}
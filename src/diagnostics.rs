@@ -0,0 +1,418 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, ToSocketAddrs},
+    process::Command,
+};
+
+use crate::{
+    cidr::Cidr,
+    config::{WgConfig, peer_names},
+    error::Error,
+    task::CancelFlag,
+    types::{DiagnosticCheck, MtuProbe, STALE_HANDSHAKE, Tunnel},
+    wireguard::{CMD_IP, read_tunnel_config},
+};
+
+const CMD_PING: &str = "ping";
+/// Seconds to wait for a ping reply.
+const PING_TIMEOUT: &str = "2";
+/// Path MTUs the probe searches between: Ethernet down to the IPv6 minimum.
+const MAX_PROBE_MTU: u16 = 1500;
+const MIN_PROBE_MTU: u16 = 1280;
+/// Pings per size before it counts as too large, so one lost reply does not.
+const PROBE_ATTEMPTS: usize = 2;
+/// UDP header plus WireGuard's 16-byte data header and 16-byte auth tag.
+const WIREGUARD_UDP_OVERHEAD: u16 = 8 + 32;
+const ICMP_HEADER: u16 = 8;
+/// Looked up to test name resolution.
+const DNS_PROBE_HOST: &str = "wireguard.com";
+/// Routed through a default-route prefix, which has no usable host of its own.
+const DEFAULT_PROBE_V4: IpAddr = IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1));
+const DEFAULT_PROBE_V6: IpAddr =
+    IpAddr::V6(Ipv6Addr::new(0x2606, 0x4700, 0x4700, 0, 0, 0, 0, 0x1111));
+
+fn check(name: String, result: Result<String, String>) -> DiagnosticCheck {
+    let (passed, detail) = match result {
+        Ok(detail) => (true, detail),
+        Err(detail) => (false, detail),
+    };
+    DiagnosticCheck {
+        name,
+        passed,
+        detail,
+    }
+}
+
+/// Runs the connectivity checklist for `tunnel`: interface state, then per
+/// peer the handshake, endpoint resolution and reachability, the peer's
+/// tunnel address and the routes of its AllowedIPs, then DNS. Stops early
+/// when cancelled.
+pub fn diagnose(tunnel: &Tunnel, cancel: &CancelFlag) -> Result<Vec<DiagnosticCheck>, Error> {
+    let content = read_tunnel_config(&tunnel.name)?;
+    let config = WgConfig::parse(&content);
+    let names = peer_names(&content);
+    let name = tunnel.name.as_str();
+    let addresses: Vec<Cidr> = config
+        .interface()
+        .map(|i| i.get_list("Address"))
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|a| a.parse().ok())
+        .collect();
+
+    let mut checks = vec![check(
+        "Interface".into(),
+        if tunnel.is_active {
+            Ok(format!("{name} is up"))
+        } else {
+            Err(format!("{name} is down; bring it up first"))
+        },
+    )];
+
+    for (i, peer) in config.peers().enumerate() {
+        let label = names
+            .get(i)
+            .filter(|n| !n.is_empty())
+            .map_or_else(|| format!("Peer {}", i + 1), Clone::clone);
+        let public_key = peer.get("PublicKey").unwrap_or_default();
+        let live = tunnel
+            .interface
+            .iter()
+            .flat_map(|i| &i.peers)
+            .find(|p| p.public_key == public_key);
+
+        checks.push(check(
+            format!("{label}: handshake"),
+            match live.and_then(|p| Some((p.handshake_age?, p.latest_handshake.as_deref()?))) {
+                Some((age, text)) if age <= STALE_HANDSHAKE => Ok(text.to_string()),
+                Some((_, text)) => {
+                    Err(format!("{text}, older than {}s", STALE_HANDSHAKE.as_secs()))
+                }
+                None => Err("No handshake yet".into()),
+            },
+        ));
+
+        if let Some(endpoint) = peer.get("Endpoint") {
+            let resolved = endpoint
+                .to_socket_addrs()
+                .map(|addrs| addrs.map(|a| a.ip()).collect::<Vec<_>>());
+            checks.push(check(
+                format!("{label}: resolve {endpoint}"),
+                match resolved {
+                    Ok(ref ips) if !ips.is_empty() => Ok(join(ips)),
+                    Ok(_) => Err("No addresses".into()),
+                    Err(ref e) => Err(e.to_string()),
+                },
+            ));
+            if let Ok(ip) = live
+                .and_then(|p| p.endpoint.as_deref())
+                .unwrap_or(endpoint)
+                .to_socket_addrs()
+                .map_err(|_| ())
+                .and_then(|mut addrs| addrs.next().ok_or(()))
+            {
+                if cancel.is_cancelled() {
+                    return Ok(checks);
+                }
+                checks.push(check(
+                    format!("{label}: ping endpoint {}", ip.ip()),
+                    ping(ip.ip()),
+                ));
+            }
+        }
+
+        let allowed: Vec<Cidr> = peer
+            .get_list("AllowedIPs")
+            .iter()
+            .filter_map(|a| a.parse().ok())
+            .collect();
+        if cancel.is_cancelled() {
+            return Ok(checks);
+        }
+        match peer_tunnel_ip(&allowed, &addresses) {
+            Some(ip) => checks.push(check(format!("{label}: ping {ip}"), ping(ip))),
+            None => checks.push(check(
+                format!("{label}: ping tunnel address"),
+                Err("No peer address can be told from AllowedIPs".into()),
+            )),
+        }
+        for prefix in &allowed {
+            if cancel.is_cancelled() {
+                return Ok(checks);
+            }
+            checks.push(check(
+                format!("{label}: route {prefix}"),
+                route_via(probe_addr(prefix), name),
+            ));
+        }
+    }
+
+    let dns = config
+        .interface()
+        .map(|i| i.get_list("DNS"))
+        .unwrap_or_default();
+    for server in dns.iter().filter_map(|s| s.parse::<IpAddr>().ok()) {
+        checks.push(check(
+            format!("DNS {server}: route"),
+            route_via(server, name),
+        ));
+    }
+    if !cancel.is_cancelled() {
+        checks.push(check(
+            format!("DNS: resolve {DNS_PROBE_HOST}"),
+            match (DNS_PROBE_HOST, 443).to_socket_addrs() {
+                Ok(addrs) => Ok(join(&addrs.map(|a| a.ip()).collect::<Vec<_>>())),
+                Err(e) => Err(e.to_string()),
+            },
+        ));
+    }
+    Ok(checks)
+}
+
+fn ip_header(ip: IpAddr) -> u16 {
+    if ip.is_ipv4() { 20 } else { 40 }
+}
+
+/// Bytes WireGuard adds to each packet sent to an endpoint at `ip`: 60 for
+/// IPv4, 80 for IPv6.
+fn wireguard_overhead(ip: IpAddr) -> u16 {
+    ip_header(ip) + WIREGUARD_UDP_OVERHEAD
+}
+
+/// Finds the largest packet that reaches each peer endpoint of `tunnel` with
+/// the Don't Fragment bit set and derives the tunnel MTU from the most
+/// limiting one. The endpoints must be reachable outside the tunnel.
+pub fn probe_mtu(tunnel: &Tunnel, cancel: &CancelFlag) -> Result<MtuProbe, Error> {
+    let config = WgConfig::parse(&read_tunnel_config(&tunnel.name)?);
+    let mut endpoints: Vec<IpAddr> = tunnel
+        .interface
+        .iter()
+        .flat_map(|i| &i.peers)
+        .filter_map(|p| p.endpoint.as_deref())
+        .chain(config.peers().filter_map(|p| p.get("Endpoint")))
+        .filter_map(|e| e.to_socket_addrs().ok()?.next())
+        .map(|a| a.ip())
+        .collect();
+    endpoints.sort();
+    endpoints.dedup();
+    if endpoints.is_empty() {
+        return Err(Error::NotFound(format!("Endpoint of '{}'", tunnel.name)));
+    }
+
+    let mut best: Option<MtuProbe> = None;
+    for ip in endpoints {
+        if let Ok(Some(dev)) = route_dev(ip)
+            && dev == tunnel.name
+        {
+            return Err(Error::WgTui(format!(
+                "{ip} is routed through {dev}; bring the tunnel down to probe the path"
+            )));
+        }
+        if ping(ip).is_err() {
+            return Err(Error::WgTui(format!(
+                "{ip} does not answer ping; its path MTU cannot be probed"
+            )));
+        }
+        let path_mtu = search_path_mtu(|mtu| !cancel.is_cancelled() && ping_unfragmented(ip, mtu));
+        if cancel.is_cancelled() {
+            return Err(Error::WgTui("MTU probe cancelled".into()));
+        }
+        let Some(path_mtu) = path_mtu else {
+            return Err(Error::WgTui(format!(
+                "Path MTU to {ip} is below {MIN_PROBE_MTU}"
+            )));
+        };
+        let overhead = wireguard_overhead(ip);
+        let recommended = path_mtu - overhead;
+        if best.as_ref().is_none_or(|b| recommended < b.recommended) {
+            best = Some(MtuProbe {
+                endpoint: ip,
+                path_mtu,
+                overhead,
+                recommended,
+                current: config
+                    .interface()
+                    .and_then(|i| i.get("MTU"))
+                    .map(Into::into),
+            });
+        }
+    }
+    Ok(best.expect("at least one endpoint was probed"))
+}
+
+/// Binary-searches the largest MTU from the probe range for which `ping`
+/// gets through, trying each size up to [`PROBE_ATTEMPTS`] times. The full
+/// size is tried first since it is the common case.
+fn search_path_mtu(mut ping: impl FnMut(u16) -> bool) -> Option<u16> {
+    let mut fits = |mtu| (0..PROBE_ATTEMPTS).any(|_| ping(mtu));
+    if fits(MAX_PROBE_MTU) {
+        return Some(MAX_PROBE_MTU);
+    }
+    if !fits(MIN_PROBE_MTU) {
+        return None;
+    }
+    // `good` always fits and `bad` never does.
+    let (mut good, mut bad) = (MIN_PROBE_MTU, MAX_PROBE_MTU);
+    while bad - good > 1 {
+        let mid = good + (bad - good) / 2;
+        if fits(mid) {
+            good = mid;
+        } else {
+            bad = mid;
+        }
+    }
+    Some(good)
+}
+
+/// Pings `ip` once with a packet of `mtu` bytes that must not be fragmented.
+fn ping_unfragmented(ip: IpAddr, mtu: u16) -> bool {
+    let payload = mtu - ip_header(ip) - ICMP_HEADER;
+    Command::new(CMD_PING)
+        .args(["-c", "1", "-W", PING_TIMEOUT, "-M", "do", "-s"])
+        .arg(payload.to_string())
+        .arg(ip.to_string())
+        .output()
+        .is_ok_and(|o| o.status.success())
+}
+
+fn join(ips: &[IpAddr]) -> String {
+    ips.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The peer's address inside the tunnel: a host prefix of its AllowedIPs,
+/// or else the first host of our own subnet when the peer routes it, as is
+/// usual for a client of a server at `.1`.
+fn peer_tunnel_ip(allowed: &[Cidr], addresses: &[Cidr]) -> Option<IpAddr> {
+    if let Some(host) = allowed.iter().find(|c| c.is_host()) {
+        return Some(host.addr());
+    }
+    addresses.iter().find_map(|own| {
+        let candidate = own.first_host();
+        let host = Cidr::new(candidate, if own.is_ipv4() { 32 } else { 128 })?;
+        (candidate != own.addr() && allowed.iter().any(|c| c.contains(&host))).then_some(candidate)
+    })
+}
+
+/// An address inside `prefix` to look up in the routing table.
+fn probe_addr(prefix: &Cidr) -> IpAddr {
+    match (prefix.is_default(), prefix.is_ipv4()) {
+        (true, true) => DEFAULT_PROBE_V4,
+        (true, false) => DEFAULT_PROBE_V6,
+        (false, _) => prefix.first_host(),
+    }
+}
+
+fn ping(ip: IpAddr) -> Result<String, String> {
+    let output = Command::new(CMD_PING)
+        .args(["-c", "1", "-W", PING_TIMEOUT])
+        .arg(ip.to_string())
+        .output()
+        .map_err(|e| format!("{CMD_PING}: {e}"))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if output.status.success() {
+        Ok(parse_ping_time(&stdout).map_or_else(|| "Reply received".into(), |t| format!("{t} ms")))
+    } else {
+        Err(format!("No reply within {PING_TIMEOUT}s"))
+    }
+}
+
+/// The round-trip time from a `ping` reply line such as
+/// `64 bytes from 10.0.0.1: icmp_seq=1 ttl=64 time=12.3 ms`.
+fn parse_ping_time(output: &str) -> Option<&str> {
+    output
+        .split_whitespace()
+        .find_map(|word| word.strip_prefix("time="))
+}
+
+/// The interface the kernel routes `ip` out of, if it has a route.
+fn route_dev(ip: IpAddr) -> Result<Option<String>, String> {
+    let output = Command::new(CMD_IP)
+        .args(["route", "get"])
+        .arg(ip.to_string())
+        .output()
+        .map_err(|e| format!("{CMD_IP}: {e}"))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(parse_route_dev(&String::from_utf8_lossy(&output.stdout)).map(str::to_string))
+}
+
+/// Checks that the kernel routes `ip` out of the interface `dev`.
+fn route_via(ip: IpAddr, dev: &str) -> Result<String, String> {
+    match route_dev(ip)? {
+        Some(found) if found == dev => Ok(format!("{ip} via {dev}")),
+        Some(found) => Err(format!("{ip} goes via {found}, not {dev}")),
+        None => Err(format!("No route to {ip}")),
+    }
+}
+
+/// The device of an `ip route get` result such as
+/// `1.1.1.1 dev wg0 table 51820 src 10.0.0.2 uid 1000`.
+fn parse_route_dev(output: &str) -> Option<&str> {
+    let mut words = output.split_whitespace();
+    words.find(|w| *w == "dev")?;
+    words.next()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn cidrs(list: &str) -> Vec<Cidr> {
+        crate::cidr::parse_list(list).unwrap()
+    }
+
+    #[test]
+    fn finds_peer_tunnel_ip() {
+        let ip = |allowed, own| peer_tunnel_ip(&cidrs(allowed), &cidrs(own)).map(|i| i.to_string());
+        assert_eq!(
+            ip("10.0.0.5/32, 192.168.1.0/24", "10.0.0.1/24"),
+            Some("10.0.0.5".into())
+        );
+        assert_eq!(
+            ip("0.0.0.0/0, ::/0", "10.0.0.2/24"),
+            Some("10.0.0.1".into())
+        );
+        assert_eq!(ip("192.168.0.0/16", "10.0.0.2/24"), None);
+        assert_eq!(ip("0.0.0.0/0", "10.0.0.1/24"), None);
+    }
+
+    #[test]
+    fn parses_command_output() {
+        assert_eq!(
+            parse_route_dev("1.1.1.1 dev wg0 table 51820 src 10.0.0.2 uid 1000\n    cache\n"),
+            Some("wg0")
+        );
+        assert_eq!(
+            parse_ping_time("64 bytes from 10.0.0.1: icmp_seq=1 ttl=64 time=12.3 ms\n"),
+            Some("12.3")
+        );
+        assert_eq!(probe_addr(&"::/0".parse().unwrap()), DEFAULT_PROBE_V6);
+    }
+
+    #[test]
+    fn searches_path_mtu() {
+        let mut sizes = vec![];
+        let mtu = search_path_mtu(|mtu| {
+            sizes.push(mtu);
+            mtu <= 1412
+        });
+        assert_eq!(mtu, Some(1412));
+        // Each size that fails is tried twice; the search needs far fewer
+        // sizes than stepping down from 1500.
+        sizes.dedup();
+        assert!(sizes.len() <= 10, "{sizes:?}");
+
+        assert_eq!(search_path_mtu(|_| true), Some(1500));
+        assert_eq!(search_path_mtu(|mtu| mtu < 1280), None);
+
+        // The first ping of every size is lost, which the retry makes up for.
+        let mut tried = HashSet::new();
+        let flaky = search_path_mtu(|mtu| !tried.insert(mtu) && mtu <= 1420);
+        assert_eq!(flaky, Some(1420));
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::PathBuf,
+    process::Command,
+};
+
+use crate::error::Error;
+
+const DEFAULT_EDITOR: &str = "vi";
+
+/// A multi-line text buffer with a cursor, used by the raw config editor.
+///
+/// The cursor column counts characters, not bytes.
+#[derive(Debug, Clone)]
+pub struct TextArea {
+    lines: Vec<String>,
+    row: usize,
+    col: usize,
+}
+
+impl TextArea {
+    pub fn new(text: &str) -> Self {
+        let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        Self {
+            lines,
+            row: 0,
+            col: 0,
+        }
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// The buffer contents with a trailing newline.
+    pub fn text(&self) -> String {
+        let mut text = self.lines.join("\n");
+        text.push('\n');
+        text
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let at = self.byte_index();
+        self.lines[self.row].insert(at, c);
+        self.col += 1;
+    }
+
+    pub fn insert_newline(&mut self) {
+        let at = self.byte_index();
+        let rest = self.lines[self.row].split_off(at);
+        self.row += 1;
+        self.col = 0;
+        self.lines.insert(self.row, rest);
+    }
+
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let at = self.byte_index();
+            self.lines[self.row].remove(at);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len();
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.col < self.line_len() {
+            let at = self.byte_index();
+            self.lines[self.row].remove(at);
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.col < self.line_len() {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    /// Moves the cursor `delta` lines, keeping the column where possible.
+    pub fn move_vertical(&mut self, delta: isize) {
+        let last = self.lines.len() as isize - 1;
+        self.row = (self.row as isize + delta).clamp(0, last) as usize;
+        self.col = self.col.min(self.line_len());
+    }
+
+    pub fn move_home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.col = self.line_len();
+    }
+
+    fn line_len(&self) -> usize {
+        self.lines[self.row].chars().count()
+    }
+
+    fn byte_index(&self) -> usize {
+        let line = &self.lines[self.row];
+        line.char_indices()
+            .nth(self.col)
+            .map_or(line.len(), |(i, _)| i)
+    }
+}
+
+/// Opens `content` in `$VISUAL`/`$EDITOR` (falling back to `vi`) and returns
+/// the edited text. The temporary copy is only readable by the owner and is
+/// removed afterwards.
+///
+/// The caller must release the terminal before calling this.
+pub fn edit_externally(name: &str, content: &str) -> Result<String, Error> {
+    let path = std::env::temp_dir().join(format!("wg-tui-{}-{name}.conf", std::process::id()));
+    let result = write_private(&path, content).and_then(|()| run_editor(&path));
+    let edited = result.and_then(|()| Ok(fs::read_to_string(&path)?));
+    let _ = fs::remove_file(&path);
+    edited
+}
+
+fn write_private(path: &PathBuf, content: &str) -> Result<(), Error> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+fn run_editor(path: &PathBuf) -> Result<(), Error> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_EDITOR.into());
+    // Allow values such as `code --wait`.
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or(DEFAULT_EDITOR);
+    let status = Command::new(program).args(parts).arg(path).status()?;
+    if !status.success() {
+        return Err(Error::CommandFailed {
+            cmd: program.into(),
+            status: status.code(),
+            stderr: String::new(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_across_lines() {
+        let mut area = TextArea::new("[Interface]\nMTU = 1420\n");
+        area.move_vertical(1);
+        area.move_end();
+        area.backspace();
+        area.backspace();
+        area.insert_char('0');
+        area.insert_char('0');
+        area.insert_newline();
+        area.insert_char('#');
+        assert_eq!(area.text(), "[Interface]\nMTU = 1400\n#\n");
+
+        area.move_home();
+        area.backspace();
+        assert_eq!(area.text(), "[Interface]\nMTU = 1400#\n");
+        assert_eq!(area.cursor(), (1, 10));
+
+        area.move_vertical(-1);
+        area.move_end();
+        area.delete();
+        assert_eq!(area.text(), "[Interface]MTU = 1400#\n");
+    }
+
+    #[test]
+    fn handles_multibyte_characters() {
+        let mut area = TextArea::new("# café");
+        area.move_end();
+        area.move_left();
+        area.delete();
+        area.insert_char('e');
+        assert_eq!(area.text(), "# cafe\n");
+    }
+}
@@ -1,13 +1,84 @@
+use std::io;
+
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("I/O error: {0}")]
-    Io(#[from] std::io::Error),
+    Io(io::Error),
 
     #[error("Zip error: {0}")]
     Zip(#[from] zip::result::ZipError),
 
+    #[error("{0} not found")]
+    NotFound(String),
+
+    #[error("{0} already exists")]
+    AlreadyExists(String),
+
+    /// A value the user entered; `field` is the config key, prefixed with
+    /// `Peer <n> ` for peer keys.
+    #[error("{field}: {reason}")]
+    InvalidField { field: String, reason: String },
+
+    #[error("{}", command_failure(.cmd, .status, .stderr))]
+    CommandFailed {
+        cmd: String,
+        status: Option<i32>,
+        stderr: String,
+    },
+
+    /// The operation needs privileges this process does not have.
+    #[error("{0}")]
+    PermissionDenied(String),
+
     #[error("WireGuard tui error: {0}")]
     WgTui(String),
 }
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied(e.to_string()),
+            _ => Self::Io(e),
+        }
+    }
+}
+
+impl Error {
+    pub fn invalid(field: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self::InvalidField {
+            field: field.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// The form field this error is about, if any.
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            Self::InvalidField { field, .. } => Some(field),
+            _ => None,
+        }
+    }
+
+    /// Process exit code for the CLI, following `sysexits.h`.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::InvalidField { .. } => 65,
+            Self::NotFound(_) => 66,
+            Self::CommandFailed { .. } => 69,
+            Self::AlreadyExists(_) => 73,
+            Self::Io(_) | Self::Zip(_) => 74,
+            Self::PermissionDenied(_) => 77,
+            Self::WgTui(_) => 1,
+        }
+    }
+}
+
+fn command_failure(cmd: &str, status: &Option<i32>, stderr: &str) -> String {
+    let status = status.map_or_else(|| "a signal".into(), |code| format!("exit code {code}"));
+    match stderr.trim() {
+        "" => format!("{cmd} failed with {status}"),
+        stderr => format!("{cmd} failed with {status}: {stderr}"),
+    }
+}
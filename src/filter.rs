@@ -0,0 +1,436 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::RangeInclusive,
+    time::Duration,
+};
+
+use crate::types::Tunnel;
+
+/// Narrows the tunnel list with a quick filter on state or type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuickFilter {
+    #[default]
+    All,
+    Active,
+    Server,
+    Stale,
+}
+
+impl QuickFilter {
+    pub fn next(self) -> Self {
+        match self {
+            Self::All => Self::Active,
+            Self::Active => Self::Server,
+            Self::Server => Self::Stale,
+            Self::Stale => Self::All,
+        }
+    }
+
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            Self::All => None,
+            Self::Active => Some("active"),
+            Self::Server => Some("servers"),
+            Self::Stale => Some("stale"),
+        }
+    }
+
+    fn matches(self, tunnel: &Tunnel) -> bool {
+        match self {
+            Self::All => true,
+            Self::Active => tunnel.is_active,
+            Self::Server => tunnel.is_server,
+            Self::Stale => tunnel.is_stale(),
+        }
+    }
+}
+
+/// Order of the tunnel list. Ties are broken by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortMode {
+    #[default]
+    Name,
+    ActiveFirst,
+    Handshake,
+    Transfer,
+    Type,
+}
+
+impl SortMode {
+    pub const ALL: [Self; 5] = [
+        Self::Name,
+        Self::ActiveFirst,
+        Self::Handshake,
+        Self::Transfer,
+        Self::Type,
+    ];
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Name => Self::ActiveFirst,
+            Self::ActiveFirst => Self::Handshake,
+            Self::Handshake => Self::Transfer,
+            Self::Transfer => Self::Type,
+            Self::Type => Self::Name,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::ActiveFirst => "active",
+            Self::Handshake => "handshake",
+            Self::Transfer => "transfer",
+            Self::Type => "type",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.label() == label)
+    }
+
+    pub fn sort(self, tunnels: &mut [Tunnel]) {
+        tunnels.sort_by(|a, b| a.name.cmp(&b.name));
+        match self {
+            Self::Name => {}
+            Self::ActiveFirst => tunnels.sort_by_key(|t| !t.is_active),
+            // Tunnels without a handshake go last.
+            Self::Handshake => {
+                tunnels.sort_by_key(|t| latest_handshake(t).unwrap_or(Duration::MAX))
+            }
+            Self::Transfer => tunnels.sort_by_key(|t| std::cmp::Reverse(total_transfer(t))),
+            Self::Type => tunnels.sort_by_key(|t| !t.is_server),
+        }
+    }
+}
+
+fn latest_handshake(tunnel: &Tunnel) -> Option<Duration> {
+    tunnel
+        .interface
+        .as_ref()?
+        .peers
+        .iter()
+        .filter_map(|p| p.handshake_age)
+        .min()
+}
+
+fn total_transfer(tunnel: &Tunnel) -> u64 {
+    tunnel.interface.as_ref().map_or(0, |i| {
+        i.peers.iter().map(|p| p.transfer_rx + p.transfer_tx).sum()
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TunnelFilter {
+    pub query: String,
+    pub quick: QuickFilter,
+}
+
+impl TunnelFilter {
+    pub fn is_empty(&self) -> bool {
+        self.query.is_empty() && self.quick == QuickFilter::All
+    }
+
+    /// Returns the indices of the tunnels that pass the filter. With a search
+    /// query the best matches come first; ties keep the list order.
+    pub fn apply(&self, tunnels: &[Tunnel]) -> Vec<usize> {
+        let mut matches: Vec<(usize, i32)> = tunnels
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| self.quick.matches(t))
+            .filter_map(|(i, t)| Some((i, self.score(t)?)))
+            .collect();
+        matches.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        matches.into_iter().map(|(i, _)| i).collect()
+    }
+
+    /// Scores the best matching field of `tunnel`: its name, peer endpoints,
+    /// peer names or tags.
+    fn score(&self, tunnel: &Tunnel) -> Option<i32> {
+        std::iter::once(&tunnel.name)
+            .chain(&tunnel.endpoints)
+            .chain(&tunnel.peer_names)
+            .chain(&tunnel.tags)
+            .filter_map(|text| fuzzy_score(&self.query, text))
+            .max()
+    }
+}
+
+/// Label of the group holding tunnels without tags.
+pub const UNTAGGED: &str = "untagged";
+
+/// One line of the tunnel list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListRow {
+    /// A tag header with the indices of its matching tunnels.
+    Group {
+        tag: String,
+        members: Vec<usize>,
+    },
+    Tunnel(usize),
+}
+
+/// Lays out `matches` (indices into `tunnels`) as list rows. Once any tunnel
+/// has tags the list is grouped by tag, with untagged tunnels last; a tunnel
+/// with several tags appears in each group. Members of `collapsed` groups
+/// are hidden.
+pub fn group_rows(
+    tunnels: &[Tunnel],
+    matches: &[usize],
+    collapsed: &HashSet<String>,
+) -> Vec<ListRow> {
+    if matches.iter().all(|&i| tunnels[i].tags.is_empty()) {
+        return matches.iter().map(|&i| ListRow::Tunnel(i)).collect();
+    }
+
+    let mut groups: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    let mut untagged = Vec::new();
+    for &i in matches {
+        if tunnels[i].tags.is_empty() {
+            untagged.push(i);
+        }
+        for tag in &tunnels[i].tags {
+            groups.entry(tag).or_default().push(i);
+        }
+    }
+
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    if !untagged.is_empty() {
+        groups.push((UNTAGGED, untagged));
+    }
+
+    let mut rows = Vec::new();
+    for (tag, members) in groups {
+        rows.push(ListRow::Group {
+            tag: tag.to_string(),
+            members: members.clone(),
+        });
+        if !collapsed.contains(tag) {
+            rows.extend(members.into_iter().map(ListRow::Tunnel));
+        }
+    }
+    rows
+}
+
+/// Names of the marked tunnels plus the rows covered by visual mode, in list
+/// order.
+pub fn marked_names(
+    tunnels: &[Tunnel],
+    rows: &[ListRow],
+    marked: &HashSet<String>,
+    visual: Option<RangeInclusive<usize>>,
+) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for (row, entry) in rows.iter().enumerate() {
+        let ListRow::Tunnel(i) = entry else {
+            continue;
+        };
+        let name = &tunnels[*i].name;
+        if (marked.contains(name) || visual.as_ref().is_some_and(|r| r.contains(&row)))
+            && !names.contains(name)
+        {
+            names.push(name.clone());
+        }
+    }
+    names
+}
+
+/// Names of the tunnels a bulk action applies to, in list order: the marked
+/// tunnels (including a pending visual range), else the members of the
+/// group on the `selected` row, else the tunnel on it.
+pub fn bulk_targets(
+    tunnels: &[Tunnel],
+    rows: &[ListRow],
+    marked: &HashSet<String>,
+    visual: Option<RangeInclusive<usize>>,
+    selected: Option<usize>,
+) -> Vec<String> {
+    let names = marked_names(tunnels, rows, marked, visual);
+    if !names.is_empty() {
+        return names;
+    }
+    match selected.and_then(|row| rows.get(row)) {
+        Some(ListRow::Group { members, .. }) => {
+            members.iter().map(|&i| tunnels[i].name.clone()).collect()
+        }
+        Some(ListRow::Tunnel(i)) => vec![tunnels[*i].name.clone()],
+        None => vec![],
+    }
+}
+
+/// Matches `query` as a case-insensitive subsequence of `text`. Consecutive
+/// characters and matches at word starts score higher.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut prev_matched = false;
+    let mut prev_char: Option<char> = None;
+
+    for c in text.chars() {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+        let matched = c.to_lowercase().eq(std::iter::once(wanted));
+        if matched {
+            query.next();
+            score += 1;
+            if prev_matched {
+                score += 5;
+            }
+            if prev_char.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 3;
+            }
+        }
+        prev_matched = matched;
+        prev_char = Some(c);
+    }
+
+    query.peek().is_none().then_some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{InterfaceInfo, PeerInfo};
+
+    fn tunnel(name: &str, endpoints: &[&str], peer_names: &[&str]) -> Tunnel {
+        Tunnel {
+            name: name.into(),
+            endpoints: endpoints.iter().map(|e| e.to_string()).collect(),
+            peer_names: peer_names.iter().map(|n| n.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fuzzy_matches_subsequences() {
+        assert!(fuzzy_score("wg0", "wg0").is_some());
+        assert!(fuzzy_score("hq", "home-quarters").is_some());
+        assert!(fuzzy_score("WG", "office-wg").is_some());
+        assert!(fuzzy_score("x", "wg0").is_none());
+        assert!(fuzzy_score("gw", "wg").is_none());
+        assert!(fuzzy_score("off", "office").unwrap() > fuzzy_score("off", "of-f").unwrap());
+    }
+
+    fn active(name: &str, handshake_secs: Option<u64>, transfer: u64) -> Tunnel {
+        Tunnel {
+            name: name.into(),
+            is_active: true,
+            interface: Some(InterfaceInfo {
+                peers: vec![PeerInfo {
+                    handshake_age: handshake_secs.map(Duration::from_secs),
+                    transfer_rx: transfer,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn sorted(mode: SortMode, tunnels: &mut [Tunnel]) -> Vec<String> {
+        mode.sort(tunnels);
+        tunnels.iter().map(|t| t.name.clone()).collect()
+    }
+
+    #[test]
+    fn sorts_by_mode() {
+        let mut tunnels = vec![
+            tunnel("c", &[], &[]),
+            active("b", Some(300), 10),
+            active("a", None, 0),
+            active("d", Some(5), 500),
+        ];
+        assert_eq!(sorted(SortMode::Name, &mut tunnels), ["a", "b", "c", "d"]);
+        assert_eq!(
+            sorted(SortMode::ActiveFirst, &mut tunnels),
+            ["a", "b", "d", "c"]
+        );
+        assert_eq!(
+            sorted(SortMode::Handshake, &mut tunnels),
+            ["d", "b", "a", "c"]
+        );
+        assert_eq!(
+            sorted(SortMode::Transfer, &mut tunnels),
+            ["d", "b", "a", "c"]
+        );
+    }
+
+    #[test]
+    fn groups_by_tag() {
+        let mut tunnels = vec![
+            tunnel("a", &[], &[]),
+            tunnel("b", &[], &[]),
+            tunnel("c", &[], &[]),
+        ];
+        let all = [0, 1, 2];
+        assert_eq!(
+            group_rows(&tunnels, &all, &HashSet::new()),
+            [ListRow::Tunnel(0), ListRow::Tunnel(1), ListRow::Tunnel(2)]
+        );
+
+        tunnels[0].tags = vec!["site".into(), "prod".into()];
+        tunnels[2].tags = vec!["site".into()];
+        let group = |tag: &str, members: &[usize]| ListRow::Group {
+            tag: tag.into(),
+            members: members.to_vec(),
+        };
+        assert_eq!(
+            group_rows(&tunnels, &all, &HashSet::from(["site".to_string()])),
+            [
+                group("prod", &[0]),
+                ListRow::Tunnel(0),
+                group("site", &[0, 2]),
+                group(UNTAGGED, &[1]),
+                ListRow::Tunnel(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn picks_bulk_targets() {
+        let mut tunnels = vec![
+            tunnel("a", &[], &[]),
+            tunnel("b", &[], &[]),
+            tunnel("c", &[], &[]),
+        ];
+        tunnels[0].tags = vec!["site".into()];
+        tunnels[2].tags = vec!["site".into()];
+        // site: a, c; untagged: b
+        let rows = group_rows(&tunnels, &[0, 1, 2], &HashSet::new());
+        let none = HashSet::new();
+        let targets = |marked: &HashSet<String>, visual, selected| {
+            bulk_targets(&tunnels, &rows, marked, visual, selected)
+        };
+
+        assert_eq!(targets(&none, None, Some(1)), ["a"]);
+        assert_eq!(targets(&none, None, Some(0)), ["a", "c"]);
+        assert!(targets(&none, None, None).is_empty());
+
+        let marked = HashSet::from(["c".to_string(), "b".to_string()]);
+        assert_eq!(targets(&marked, None, Some(1)), ["c", "b"]);
+        // The visual range covers the rows of a and c; a header adds nothing.
+        assert_eq!(targets(&none, Some(0..=2), Some(2)), ["a", "c"]);
+        assert_eq!(targets(&marked, Some(1..=1), Some(1)), ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn searches_endpoints_and_peer_names() {
+        let tunnels = [
+            tunnel("wg0", &["vpn.example.com:51820"], &[]),
+            tunnel("wg1", &[], &["laptop"]),
+            tunnel("office", &[], &[]),
+        ];
+        let filter = |query: &str| {
+            TunnelFilter {
+                query: query.into(),
+                quick: QuickFilter::All,
+            }
+            .apply(&tunnels)
+        };
+        assert_eq!(filter(""), [0, 1, 2]);
+        assert_eq!(filter("example"), [0]);
+        assert_eq!(filter("lap"), [1]);
+        assert_eq!(filter("of"), [2]);
+    }
+}
@@ -0,0 +1,479 @@
+use std::{collections::HashMap, fs};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+use crate::{error::Error, settings::config_path};
+
+const KEYMAP_FILE: &str = "keys.toml";
+
+/// A remappable action of the tunnel list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Down,
+    Up,
+    First,
+    Last,
+    Search,
+    QuickFilter,
+    Sort,
+    Edit,
+    EditRaw,
+    Details,
+    Diagnose,
+    ProbeMtu,
+    Add,
+    Tags,
+    Collapse,
+    Expand,
+    Mark,
+    Visual,
+    BringUp,
+    BringDown,
+    Rename,
+    Clone,
+    Export,
+    Delete,
+    AddPeer,
+    Toggle,
+    Refresh,
+    PauseRefresh,
+    Settings,
+    Log,
+    Help,
+    Quit,
+}
+
+impl Action {
+    /// Whether the action changes tunnels, their configs or other files on
+    /// disk; these are unbound in read-only mode. Edit stays bound there and
+    /// toggles groups or opens the details pane instead.
+    pub fn is_mutating(self) -> bool {
+        matches!(
+            self,
+            Self::EditRaw
+                | Self::Add
+                | Self::Tags
+                | Self::BringUp
+                | Self::BringDown
+                | Self::Rename
+                | Self::Clone
+                | Self::Delete
+                | Self::AddPeer
+                | Self::Toggle
+                | Self::Export
+                | Self::Settings
+        )
+    }
+}
+
+/// Every action with its name in `keys.toml`, help text and default keys, in
+/// the order shown by the help popup.
+const ACTIONS: &[(Action, &str, &str, &[&str])] = &[
+    (Action::Down, "down", "Move down", &["j", "down"]),
+    (Action::Up, "up", "Move up", &["k", "up"]),
+    (Action::First, "first", "First tunnel", &["g"]),
+    (Action::Last, "last", "Last tunnel", &["G"]),
+    (Action::Search, "search", "Search", &["/"]),
+    (
+        Action::QuickFilter,
+        "quick_filter",
+        "Filter: active / servers / stale",
+        &["f"],
+    ),
+    (
+        Action::Sort,
+        "sort",
+        "Sort: name / active / handshake / transfer / type",
+        &["s"],
+    ),
+    (Action::Edit, "edit", "Edit tunnel config", &["enter"]),
+    (Action::EditRaw, "edit_raw", "Edit raw config", &["E"]),
+    (Action::Details, "details", "Toggle details", &["d"]),
+    (
+        Action::Diagnose,
+        "diagnose",
+        "Connectivity diagnostics",
+        &["D"],
+    ),
+    (
+        Action::ProbeMtu,
+        "probe_mtu",
+        "Probe path MTU and suggest MTU",
+        &["M"],
+    ),
+    (Action::Add, "add", "Add tunnel", &["a"]),
+    (Action::Tags, "tags", "Edit tags", &["T"]),
+    (
+        Action::Collapse,
+        "collapse",
+        "Collapse group",
+        &["h", "left"],
+    ),
+    (Action::Expand, "expand", "Expand group", &["l", "right"]),
+    (Action::Mark, "mark", "Mark tunnel / group", &["space"]),
+    (
+        Action::Visual,
+        "visual",
+        "Visual mode (mark a range)",
+        &["v"],
+    ),
+    (
+        Action::BringUp,
+        "bring_up",
+        "Bring marked / group up",
+        &["+"],
+    ),
+    (
+        Action::BringDown,
+        "bring_down",
+        "Bring marked / group down",
+        &["-"],
+    ),
+    (Action::Rename, "rename", "Rename tunnel", &["n"]),
+    (Action::Clone, "clone", "Clone tunnel", &["c"]),
+    (
+        Action::Export,
+        "export",
+        "Export marked or all to zip",
+        &["e"],
+    ),
+    (Action::Delete, "delete", "Delete tunnel(s)", &["x"]),
+    (
+        Action::AddPeer,
+        "add_peer",
+        "Add peer (server only)",
+        &["p"],
+    ),
+    (Action::Toggle, "toggle", "Toggle tunnel", &["t"]),
+    (Action::Refresh, "refresh", "Refresh", &["r"]),
+    (
+        Action::PauseRefresh,
+        "pause_refresh",
+        "Pause / resume auto-refresh",
+        &["P"],
+    ),
+    (Action::Settings, "settings", "Settings", &[","]),
+    (Action::Log, "log", "Activity log", &["L"]),
+    (Action::Help, "help", "Help", &["?"]),
+    (Action::Quit, "quit", "Quit", &["q", "esc"]),
+];
+
+/// A key with its modifiers. Shift is implied by the character for printable
+/// keys, so `G` matches Shift+g.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Parses names such as `j`, `G`, `space`, `pagedown` or `ctrl+n`.
+    /// `shift+` on a letter means its uppercase form; terminals report other
+    /// shifted characters as the character they type, so those are refused.
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let invalid = || Error::WgTui(format!("Invalid key '{s}'"));
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s.trim();
+        // A lone "+" or "-" is a key, not a modifier separator.
+        while rest.len() > 1
+            && let Some((modifier, key)) = rest.split_once('+')
+        {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(invalid()),
+            };
+            rest = key;
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                name => name
+                    .strip_prefix('f')
+                    .and_then(|n| n.parse().ok())
+                    .filter(|n| (1..=12).contains(n))
+                    .map(KeyCode::F)
+                    .ok_or_else(invalid)?,
+            },
+        };
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                if !c.is_alphabetic() {
+                    return Err(Error::WgTui(format!(
+                        "Invalid key '{s}': write the shifted character instead"
+                    )));
+                }
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_uppercase().next().unwrap_or(c))
+            }
+            code => code,
+        };
+        Ok(Self { code, modifiers })
+    }
+
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        let mut modifiers = key.modifiers;
+        if matches!(key.code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        self.code == key.code && self.modifiers == modifiers
+    }
+
+    /// Short label for the help popup and status bar.
+    pub fn label(&self) -> String {
+        let key = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Up => "↑".into(),
+            KeyCode::Down => "↓".into(),
+            KeyCode::Left => "←".into(),
+            KeyCode::Right => "→".into(),
+            KeyCode::PageUp => "PgUp".into(),
+            KeyCode::PageDown => "PgDn".into(),
+            KeyCode::F(n) => format!("F{n}"),
+            code => code.to_string(),
+        };
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push('^');
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("M-");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            label.push_str("S-");
+        }
+        label + &key
+    }
+}
+
+/// One key or a list of keys in `keys.toml`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeySpec {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Keys for the tunnel list actions. Popups and prompts keep their fixed keys.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<KeyBinding>)>,
+    read_only: bool,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = ACTIONS
+            .iter()
+            .map(|(action, _, _, keys)| {
+                let keys = keys
+                    .iter()
+                    .map(|k| KeyBinding::parse(k).expect("valid default key"))
+                    .collect();
+                (*action, keys)
+            })
+            .collect();
+        Self {
+            bindings,
+            read_only: false,
+        }
+    }
+}
+
+impl Keymap {
+    /// Loads `$XDG_CONFIG_HOME/wg-tui/keys.toml` over the defaults. A missing
+    /// file gives the defaults.
+    pub fn load() -> Result<Self, Error> {
+        let Some(path) = config_path(KEYMAP_FILE).filter(|p| p.exists()) else {
+            return Ok(Self::default());
+        };
+        let content = fs::read_to_string(&path)?;
+        Self::parse(&content).map_err(|e| Error::WgTui(format!("{}: {e}", path.display())))
+    }
+
+    /// Parses `action = "key"` or `action = ["key", ...]` lines. A key bound
+    /// to an action is taken away from every other action.
+    pub fn parse(content: &str) -> Result<Self, Error> {
+        let overrides: HashMap<String, KeySpec> =
+            toml::from_str(content).map_err(|e| Error::WgTui(e.message().to_string()))?;
+        let mut keymap = Self::default();
+        for (name, spec) in overrides {
+            let action = ACTIONS
+                .iter()
+                .find(|(_, n, _, _)| *n == name)
+                .map(|(action, ..)| *action)
+                .ok_or_else(|| Error::WgTui(format!("Unknown action '{name}'")))?;
+            let keys = match spec {
+                KeySpec::One(key) => vec![key],
+                KeySpec::Many(keys) => keys,
+            };
+            let keys = keys
+                .iter()
+                .map(|k| KeyBinding::parse(k))
+                .collect::<Result<Vec<_>, _>>()?;
+            for (other, bound) in &mut keymap.bindings {
+                if *other == action {
+                    bound.clone_from(&keys);
+                } else {
+                    bound.retain(|b| !keys.contains(b));
+                }
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Unbinds every mutating action, which also drops it from the help.
+    pub fn without_mutating(mut self) -> Self {
+        self.read_only = true;
+        for (action, keys) in &mut self.bindings {
+            if action.is_mutating() {
+                keys.clear();
+            }
+        }
+        self
+    }
+
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|k| k.matches(key)))
+            .map(|(action, _)| *action)
+    }
+
+    /// All keys of `action` joined for display, e.g. `j / ↓`.
+    pub fn label(&self, action: Action) -> String {
+        self.keys(action)
+            .iter()
+            .map(KeyBinding::label)
+            .collect::<Vec<_>>()
+            .join(" / ")
+    }
+
+    /// The first key of `action`, for compact hints.
+    pub fn short_label(&self, action: Action) -> String {
+        self.keys(action)
+            .first()
+            .map_or_else(|| "-".into(), KeyBinding::label)
+    }
+
+    /// Key labels and descriptions of every bound action, in help order.
+    pub fn help(&self) -> Vec<(String, &'static str)> {
+        ACTIONS
+            .iter()
+            .filter(|(action, ..)| !self.keys(*action).is_empty())
+            .map(|(action, _, description, _)| {
+                let description = match action {
+                    Action::Edit if self.read_only => "Toggle group / show details",
+                    _ => description,
+                };
+                (self.label(*action), description)
+            })
+            .collect()
+    }
+
+    fn keys(&self, action: Action) -> &[KeyBinding] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map_or(&[], |(_, keys)| keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_key_names() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        assert!(
+            KeyBinding::parse("ctrl+n")
+                .unwrap()
+                .matches(&key(KeyCode::Char('n'), KeyModifiers::CONTROL))
+        );
+        assert!(
+            KeyBinding::parse("G")
+                .unwrap()
+                .matches(&key(KeyCode::Char('G'), KeyModifiers::SHIFT))
+        );
+        assert!(
+            KeyBinding::parse("+")
+                .unwrap()
+                .matches(&key(KeyCode::Char('+'), KeyModifiers::NONE))
+        );
+        assert_eq!(KeyBinding::parse("pagedown").unwrap().label(), "PgDn");
+        assert_eq!(KeyBinding::parse("F5").unwrap().label(), "F5");
+        assert!(KeyBinding::parse("hyper+x").is_err());
+        assert!(KeyBinding::parse("foo").is_err());
+    }
+
+    #[test]
+    fn remaps_actions() {
+        let keymap = Keymap::parse("toggle = [\"t\", \"space\"]\nquit = \"ctrl+q\"\n").unwrap();
+        let space = KeyEvent::from(KeyCode::Char(' '));
+        assert_eq!(keymap.action(&space), Some(Action::Toggle));
+        assert_eq!(keymap.label(Action::Toggle), "t / Space");
+        assert!(
+            !keymap
+                .help()
+                .iter()
+                .any(|(_, d)| *d == "Mark tunnel / group")
+        );
+        assert_eq!(keymap.action(&KeyEvent::from(KeyCode::Char('q'))), None);
+        assert_eq!(keymap.short_label(Action::Quit), "^q");
+
+        let read_only = keymap.without_mutating();
+        assert_eq!(read_only.action(&space), None);
+        for key in ['e', ','] {
+            assert_eq!(read_only.action(&KeyEvent::from(KeyCode::Char(key))), None);
+        }
+        assert_eq!(
+            read_only.action(&KeyEvent::from(KeyCode::Char('L'))),
+            Some(Action::Log)
+        );
+        assert!(
+            !read_only
+                .help()
+                .iter()
+                .any(|(_, d)| *d == "Delete tunnel(s)")
+        );
+        // Enter stays bound; the app opens details instead of the edit form.
+        let enter = KeyEvent::from(KeyCode::Enter);
+        assert_eq!(read_only.action(&enter), Some(Action::Edit));
+        assert!(
+            read_only
+                .help()
+                .iter()
+                .any(|(_, d)| *d == "Toggle group / show details")
+        );
+
+        let shift_g = Keymap::parse("last = \"shift+g\"").unwrap();
+        let shifted = KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(shift_g.action(&shifted), Some(Action::Last));
+        assert_eq!(shift_g.label(Action::Last), "G");
+        assert!(Keymap::parse("last = \"shift+1\"").is_err());
+
+        assert!(Keymap::parse("fly = \"x\"").is_err());
+        assert!(Keymap::parse("quit = \"ctrl+\"").is_err());
+    }
+}
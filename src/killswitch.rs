@@ -0,0 +1,363 @@
+use std::net::{IpAddr, ToSocketAddrs};
+
+use crate::{config::WgConfig, error::Error};
+
+/// Every generated rule references a chain/table with this prefix, which is
+/// also how an existing kill switch is recognised.
+const MARKER: &str = "wg-tui-ks";
+
+const CMD_IPTABLES: &str = "iptables";
+const CMD_IP6TABLES: &str = "ip6tables";
+const CMD_NFT: &str = "nft";
+
+const LAN_V4: &[&str] = &["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16"];
+const LAN_V6: &[&str] = &["fe80::/10", "fc00::/7"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    /// `ip6` is false without ip6tables, e.g. on hosts with IPv6 disabled,
+    /// where its rules would fail the bring-up.
+    Iptables {
+        ip6: bool,
+    },
+    Nftables,
+}
+
+impl Backend {
+    /// Prefers iptables, which the generated server configs already use.
+    fn detect() -> Self {
+        if which::which(CMD_IPTABLES).is_err() && which::which(CMD_NFT).is_ok() {
+            Self::Nftables
+        } else {
+            Self::Iptables {
+                ip6: which::which(CMD_IP6TABLES).is_ok(),
+            }
+        }
+    }
+}
+
+/// A destination the kill switch lets through. `host` is the name `addr`
+/// was resolved from; it is written as a rule comment so stale addresses
+/// can be recognised.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Allowed {
+    addr: IpAddr,
+    port: Option<u16>,
+    host: Option<String>,
+}
+
+pub fn has_kill_switch(config: &WgConfig) -> bool {
+    config
+        .interface()
+        .is_some_and(|i| i.get_all("PostUp").any(|v| v.contains(MARKER)))
+}
+
+/// Rewrites `content` so it has exactly one kill switch that lets through
+/// every endpoint in `endpoints`, or none if there are none. Other lines are
+/// left untouched. Host names are resolved now and their addresses are
+/// written into the rules, so saving again picks up changed addresses.
+pub fn set_kill_switch(content: &str, endpoints: &[&str]) -> Result<String, Error> {
+    let mut allowed = Vec::new();
+    for endpoint in endpoints {
+        allowed.extend(resolve_endpoint(endpoint)?);
+    }
+    allowed.dedup();
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_interface = false;
+    let mut interface_end = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            in_interface = trimmed.eq_ignore_ascii_case("[Interface]");
+        } else if in_interface && is_kill_switch_line(trimmed) {
+            continue;
+        }
+        lines.push(line.to_string());
+        if in_interface && !trimmed.is_empty() {
+            interface_end = Some(lines.len());
+        }
+    }
+
+    if let Some(end) = interface_end
+        && !allowed.is_empty()
+    {
+        let (post_up, pre_down) = rules(Backend::detect(), &allowed);
+        let hooks = [
+            format!("PostUp = {post_up}"),
+            format!("PreDown = {pre_down}"),
+        ];
+        lines.splice(end..end, hooks);
+    }
+
+    let mut result = lines.join("\n");
+    if content.ends_with('\n') || content.is_empty() {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+pub fn is_kill_switch_line(line: &str) -> bool {
+    let Some((key, value)) = line.split_once('=') else {
+        return false;
+    };
+    let key = key.trim();
+    (key.eq_ignore_ascii_case("PostUp") || key.eq_ignore_ascii_case("PreDown"))
+        && value.contains(MARKER)
+}
+
+/// Builds the PostUp/PreDown commands that reject all egress except through
+/// the tunnel, to the peer endpoints, loopback and private LAN ranges.
+fn rules(backend: Backend, allowed: &[Allowed]) -> (String, String) {
+    match backend {
+        Backend::Iptables { ip6 } => {
+            let chain = format!("{MARKER}-%i");
+            let mut up = Vec::new();
+            let mut down = Vec::new();
+            let families = [(CMD_IPTABLES, LAN_V4, false), (CMD_IP6TABLES, LAN_V6, true)];
+            for (cmd, lan, family_v6) in families.into_iter().filter(|f| ip6 || !f.2) {
+                up.push(format!("{cmd} -N {chain}"));
+                up.push(format!("{cmd} -A {chain} -o %i -j RETURN"));
+                up.push(format!("{cmd} -A {chain} -o lo -j RETURN"));
+                up.push(format!("{cmd} -A {chain} -d {} -j RETURN", lan.join(",")));
+                for a in allowed.iter().filter(|a| a.addr.is_ipv6() == family_v6) {
+                    let dport = a
+                        .port
+                        .map(|p| format!(" -p udp --dport {p}"))
+                        .unwrap_or_default();
+                    let comment = a
+                        .host
+                        .as_ref()
+                        .map(|h| format!(" -m comment --comment {h}"))
+                        .unwrap_or_default();
+                    up.push(format!(
+                        "{cmd} -A {chain} -d {}{dport}{comment} -j RETURN",
+                        a.addr
+                    ));
+                }
+                up.push(format!("{cmd} -A {chain} -j REJECT"));
+                up.push(format!("{cmd} -I OUTPUT -j {chain}"));
+                down.push(format!("{cmd} -D OUTPUT -j {chain}"));
+                down.push(format!("{cmd} -F {chain}"));
+                down.push(format!("{cmd} -X {chain}"));
+            }
+            (up.join("; "), down.join("; "))
+        }
+        Backend::Nftables => {
+            let table = format!("inet {MARKER}-%i");
+            let mut up = vec![
+                format!("nft add table {table}"),
+                format!(
+                    "nft add chain {table} output '{{ type filter hook output priority 0; policy accept; }}'"
+                ),
+                format!("nft add rule {table} output oifname \"%i\" accept"),
+                format!("nft add rule {table} output oifname \"lo\" accept"),
+                format!(
+                    "nft add rule {table} output ip daddr {{ {} }} accept",
+                    LAN_V4.join(", ")
+                ),
+                format!(
+                    "nft add rule {table} output ip6 daddr {{ {} }} accept",
+                    LAN_V6.join(", ")
+                ),
+            ];
+            up.extend(allowed.iter().map(|a| {
+                let family = if a.addr.is_ipv6() { "ip6" } else { "ip" };
+                let dport = a
+                    .port
+                    .map(|p| format!(" udp dport {p}"))
+                    .unwrap_or_default();
+                let comment = a
+                    .host
+                    .as_ref()
+                    .map(|h| format!(" comment '\"{h}\"'"))
+                    .unwrap_or_default();
+                format!(
+                    "nft add rule {table} output {family} daddr {}{dport} accept{comment}",
+                    a.addr
+                )
+            }));
+            up.push(format!("nft add rule {table} output reject"));
+            (up.join("; "), format!("nft delete table {table}"))
+        }
+    }
+}
+
+/// The addresses and UDP port the kill switch lets through for `endpoint`.
+/// Only IP addresses and host names are accepted, so nothing else can end up
+/// in the shell commands.
+fn resolve_endpoint(endpoint: &str) -> Result<Vec<Allowed>, Error> {
+    let (host, port) = split_endpoint(endpoint);
+    let port = port
+        .map(|p| {
+            p.parse::<u16>()
+                .map_err(|_| Error::invalid("Endpoint", "must end in a valid port"))
+        })
+        .transpose()?;
+    if let Ok(addr) = host.parse::<IpAddr>() {
+        return Ok(vec![Allowed {
+            addr,
+            port,
+            host: None,
+        }]);
+    }
+    let is_host_name = !host.is_empty()
+        && host.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if !is_host_name {
+        return Err(Error::invalid(
+            "Endpoint",
+            "must be an IP address or host name with a port",
+        ));
+    }
+    let mut addrs: Vec<IpAddr> = (host, port.unwrap_or(0))
+        .to_socket_addrs()
+        .map_err(|e| Error::invalid("Endpoint", format!("cannot be resolved: {e}")))?
+        .map(|a| a.ip())
+        .collect();
+    addrs.sort();
+    addrs.dedup();
+    Ok(addrs
+        .into_iter()
+        .map(|addr| Allowed {
+            addr,
+            port,
+            host: Some(host.to_string()),
+        })
+        .collect())
+}
+
+/// Splits `host:port`, `[v6]:port` or a bare host.
+fn split_endpoint(endpoint: &str) -> (&str, Option<&str>) {
+    let endpoint = endpoint.trim();
+    if let Some(rest) = endpoint.strip_prefix('[')
+        && let Some((host, port)) = rest.split_once(']')
+    {
+        return (host, port.strip_prefix(':'));
+    }
+    match endpoint.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') => (host, Some(port)),
+        _ => (endpoint, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: &str = "[Interface]\nPrivateKey = k\nAddress = 10.0.0.2/32\n\n[Peer]\nPublicKey = p\nEndpoint = 1.2.3.4:51820\n";
+
+    fn allowed(addrs: &[&str], port: Option<u16>) -> Vec<Allowed> {
+        addrs
+            .iter()
+            .map(|a| Allowed {
+                addr: a.parse().unwrap(),
+                port,
+                host: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_endpoints() {
+        assert_eq!(split_endpoint("1.2.3.4:51820"), ("1.2.3.4", Some("51820")));
+        assert_eq!(
+            split_endpoint("[2001:db8::1]:51820"),
+            ("2001:db8::1", Some("51820"))
+        );
+        assert_eq!(
+            split_endpoint(" vpn.example.com "),
+            ("vpn.example.com", None)
+        );
+        assert_eq!(split_endpoint("2001:db8::1"), ("2001:db8::1", None));
+    }
+
+    #[test]
+    fn resolves_only_addresses_and_host_names() {
+        assert_eq!(
+            resolve_endpoint("[2001:db8::1]:51820").unwrap(),
+            allowed(&["2001:db8::1"], Some(51820))
+        );
+        assert_eq!(
+            resolve_endpoint("1.2.3.4").unwrap(),
+            allowed(&["1.2.3.4"], None)
+        );
+        let local = resolve_endpoint("localhost:51820").unwrap();
+        assert!(
+            local
+                .iter()
+                .all(|a| a.port == Some(51820) && a.host.as_deref() == Some("localhost"))
+        );
+        for endpoint in ["1.2.3.4:51820; reboot", "$(reboot):1", "1.2.3.4:port", ""] {
+            assert!(resolve_endpoint(endpoint).is_err(), "{endpoint}");
+        }
+    }
+
+    #[test]
+    fn builds_iptables_rules_per_family() {
+        let both = allowed(&["1.2.3.4", "2001:db8::1"], Some(51820));
+        let (up, down) = rules(Backend::Iptables { ip6: true }, &both);
+        assert!(up.contains("iptables -A wg-tui-ks-%i -d 1.2.3.4 -p udp --dport 51820 -j RETURN"));
+        assert!(
+            up.contains("ip6tables -A wg-tui-ks-%i -d 2001:db8::1 -p udp --dport 51820 -j RETURN")
+        );
+        assert!(!up.contains("iptables -A wg-tui-ks-%i -d 2001:db8::1"));
+        assert!(up.ends_with("ip6tables -I OUTPUT -j wg-tui-ks-%i"));
+        assert_eq!(
+            down,
+            "iptables -D OUTPUT -j wg-tui-ks-%i; iptables -F wg-tui-ks-%i; iptables -X wg-tui-ks-%i; \
+             ip6tables -D OUTPUT -j wg-tui-ks-%i; ip6tables -F wg-tui-ks-%i; ip6tables -X wg-tui-ks-%i"
+        );
+
+        // Without ip6tables only the IPv4 chain is set up.
+        let (up, down) = rules(Backend::Iptables { ip6: false }, &both);
+        assert!(!up.contains("ip6tables"));
+        assert!(!down.contains("ip6tables"));
+        assert!(up.ends_with("iptables -I OUTPUT -j wg-tui-ks-%i"));
+
+        let mut named = allowed(&["1.2.3.4"], None);
+        named[0].host = Some("vpn.example.com".into());
+        let (up, _) = rules(Backend::Iptables { ip6: true }, &named);
+        assert!(up.contains(
+            "iptables -A wg-tui-ks-%i -d 1.2.3.4 -m comment --comment vpn.example.com -j RETURN"
+        ));
+        assert!(!up.contains("ip6tables -A wg-tui-ks-%i -d 1.2.3.4"));
+    }
+
+    #[test]
+    fn builds_nft_rules_per_family() {
+        let mut both = allowed(&["1.2.3.4", "2001:db8::1"], Some(51820));
+        both[1].host = Some("vpn.example.com".into());
+        let (up, down) = rules(Backend::Nftables, &both);
+        assert!(up.contains(
+            "nft add rule inet wg-tui-ks-%i output ip daddr 1.2.3.4 udp dport 51820 accept;"
+        ));
+        assert!(up.contains(
+            "nft add rule inet wg-tui-ks-%i output ip6 daddr 2001:db8::1 udp dport 51820 accept \
+             comment '\"vpn.example.com\"'"
+        ));
+        assert!(up.ends_with("nft add rule inet wg-tui-ks-%i output reject"));
+        assert_eq!(down, "nft delete table inet wg-tui-ks-%i");
+    }
+
+    #[test]
+    fn sets_kill_switch_once() {
+        let on = set_kill_switch(CLIENT, &["1.2.3.4:51820"]).unwrap();
+        assert!(has_kill_switch(&WgConfig::parse(&on)));
+        let interface = on.split("[Peer]").next().unwrap();
+        assert_eq!(
+            interface.lines().filter(|l| is_kill_switch_line(l)).count(),
+            2
+        );
+
+        assert_eq!(set_kill_switch(&on, &["1.2.3.4:51820"]).unwrap(), on);
+        assert_eq!(set_kill_switch(&on, &[]).unwrap(), CLIENT);
+        assert_eq!(set_kill_switch(CLIENT, &[]).unwrap(), CLIENT);
+        assert!(set_kill_switch(CLIENT, &["1.2.3.4:51820;id"]).is_err());
+
+        let two = set_kill_switch(CLIENT, &["1.2.3.4:51820", "5.6.7.8:51821"]).unwrap();
+        assert!(two.contains("-d 1.2.3.4") || two.contains("daddr 1.2.3.4"));
+        assert!(two.contains("-d 5.6.7.8") || two.contains("daddr 5.6.7.8"));
+    }
+}
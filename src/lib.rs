@@ -1,9 +1,29 @@
+mod activity;
 mod app;
+mod cidr;
+mod config;
+mod diagnostics;
+mod editor;
 mod error;
+mod filter;
+mod keymap;
+mod killswitch;
+mod lint;
+mod privileged;
+mod report;
+mod settings;
+mod state;
+mod task;
+mod theme;
 mod types;
 mod ui;
 mod wireguard;
 
 pub use app::App;
+pub use error::Error;
+pub use lint::{Lint, Severity};
+pub use privileged::{helper_settings, serve as serve_helper, set_read_only, start_helper};
+pub use report::{serve_exporter, to_json, to_text};
+pub use settings::{Settings, set_settings};
 pub use types::{InterfaceInfo, Message, PeerInfo, Tunnel};
-pub use wireguard::check_dependencies;
+pub use wireguard::{check_dependencies, load_tunnels};
@@ -0,0 +1,279 @@
+use crate::{
+    cidr::Cidr,
+    config::{Section, SectionKind, WgConfig},
+};
+
+const INTERFACE_KEYS: &[&str] = &[
+    "PrivateKey",
+    "ListenPort",
+    "FwMark",
+    "Address",
+    "DNS",
+    "MTU",
+    "Table",
+    "PreUp",
+    "PreDown",
+    "PostUp",
+    "PostDown",
+    "SaveConfig",
+];
+
+const PEER_KEYS: &[&str] = &[
+    "PublicKey",
+    "PresharedKey",
+    "AllowedIPs",
+    "Endpoint",
+    "PersistentKeepalive",
+];
+
+const BASE64_ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Lint {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Lint {
+    fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+/// Checks a single config for problems `wg`/`wg-quick` would reject or that
+/// silently break routing.
+pub fn lint_config(config: &WgConfig) -> Vec<Lint> {
+    let mut lints = Vec::new();
+
+    let Some(interface) = config.interface() else {
+        return vec![Lint::error("Missing [Interface] section")];
+    };
+
+    for section in &config.sections {
+        lints.extend(lint_unknown_keys(section));
+    }
+
+    match interface.get("PrivateKey") {
+        Some(key) if !is_valid_key(key) => {
+            lints.push(Lint::error("Interface PrivateKey is not a valid key"));
+        }
+        None => lints.push(Lint::error("Interface is missing PrivateKey")),
+        _ => {}
+    }
+
+    let peers: Vec<_> = config.peers().collect();
+    let is_client = interface.get("ListenPort").is_none();
+
+    for (i, peer) in peers.iter().enumerate() {
+        let label = peer_label(i);
+        match peer.get("PublicKey") {
+            Some(key) if !is_valid_key(key) => {
+                lints.push(Lint::error(format!("{label} PublicKey is not a valid key")));
+            }
+            None => lints.push(Lint::error(format!("{label} is missing PublicKey"))),
+            _ => {}
+        }
+        if let Some(key) = peer.get("PresharedKey")
+            && !is_valid_key(key)
+        {
+            lints.push(Lint::error(format!(
+                "{label} PresharedKey is not a valid key"
+            )));
+        }
+        if is_client && peer.get("Endpoint").is_none() {
+            lints.push(Lint::warning(format!("{label} has no Endpoint")));
+        }
+        for value in peer.get_list("AllowedIPs") {
+            if let Err(e) = value.parse::<Cidr>() {
+                lints.push(Lint::error(format!("{label} AllowedIPs: {e}")));
+            }
+        }
+    }
+
+    lints.extend(lint_duplicate_keys(&peers));
+    lints.extend(lint_peer_addresses(interface, &peers));
+    lints
+}
+
+/// Flags other tunnels that would bind the same UDP port.
+pub fn lint_listen_port<'a>(
+    config: &WgConfig,
+    others: impl IntoIterator<Item = (&'a str, &'a WgConfig)>,
+) -> Vec<Lint> {
+    let Some(port) = listen_port(config) else {
+        return vec![];
+    };
+    others
+        .into_iter()
+        .filter(|(_, other)| listen_port(other) == Some(port))
+        .map(|(name, _)| Lint::warning(format!("ListenPort {port} is also used by '{name}'")))
+        .collect()
+}
+
+fn listen_port(config: &WgConfig) -> Option<u16> {
+    config.interface()?.get("ListenPort")?.parse().ok()
+}
+
+fn lint_unknown_keys(section: &Section) -> Vec<Lint> {
+    let known = match section.kind {
+        SectionKind::Interface => INTERFACE_KEYS,
+        SectionKind::Peer => PEER_KEYS,
+        SectionKind::Other => {
+            return vec![Lint::error(format!("Unknown section {}", section.header))];
+        }
+    };
+    section
+        .entries
+        .iter()
+        .filter(|e| !known.iter().any(|k| k.eq_ignore_ascii_case(&e.key)))
+        .map(|e| Lint::error(format!("Unknown key '{}' in {}", e.key, section.header)))
+        .collect()
+}
+
+fn lint_duplicate_keys(peers: &[&Section]) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    for (i, peer) in peers.iter().enumerate() {
+        let Some(key) = peer.get("PublicKey") else {
+            continue;
+        };
+        if let Some(j) = peers[..i]
+            .iter()
+            .position(|p| p.get("PublicKey") == Some(key))
+        {
+            lints.push(Lint::error(format!(
+                "{} and {} have the same PublicKey",
+                peer_label(j),
+                peer_label(i)
+            )));
+        }
+    }
+    lints
+}
+
+fn lint_peer_addresses(interface: &Section, peers: &[&Section]) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    let parsed: Vec<Vec<Cidr>> = peers.iter().map(|p| parse_cidrs(p, "AllowedIPs")).collect();
+
+    for (i, a) in parsed.iter().enumerate() {
+        for (j, b) in parsed.iter().enumerate().skip(i + 1) {
+            for x in a {
+                for y in b.iter().filter(|y| x.overlaps(y)) {
+                    let (pi, pj) = (peer_label(i), peer_label(j));
+                    lints.push(if x.is_host() && y.is_host() {
+                        Lint::error(format!("{pi} and {pj} share address {}", x.addr()))
+                    } else if x.network() == y.network() {
+                        Lint::error(format!("{pi} and {pj} both route {}", x.network()))
+                    } else {
+                        Lint::warning(format!("AllowedIPs overlap: {pi} {x} and {pj} {y}"))
+                    });
+                }
+            }
+        }
+    }
+
+    for own in parse_cidrs(interface, "Address") {
+        for (i, allowed) in parsed.iter().enumerate() {
+            if allowed
+                .iter()
+                .any(|c| c.is_host() && c.addr() == own.addr())
+            {
+                lints.push(Lint::error(format!(
+                    "{} uses the interface address {}",
+                    peer_label(i),
+                    own.addr()
+                )));
+            }
+        }
+    }
+
+    lints
+}
+
+fn parse_cidrs(section: &Section, key: &str) -> Vec<Cidr> {
+    section
+        .get_list(key)
+        .iter()
+        .filter_map(|v| v.parse().ok())
+        .collect()
+}
+
+fn peer_label(index: usize) -> String {
+    format!("Peer #{}", index + 1)
+}
+
+/// A WireGuard key is 32 bytes of standard base64: 43 characters plus `=`,
+/// with the unused low bits of the last character zero.
+pub fn is_valid_key(key: &str) -> bool {
+    let bytes = key.as_bytes();
+    if bytes.len() != 44 || bytes[43] != b'=' {
+        return false;
+    }
+    let Some(values) = bytes[..43]
+        .iter()
+        .map(|b| BASE64_ALPHABET.find(char::from(*b)))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return false;
+    };
+    values[42] & 0b11 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(content: &str) -> Vec<String> {
+        lint_config(&WgConfig::parse(content))
+            .into_iter()
+            .map(|l| l.message)
+            .collect()
+    }
+
+    #[test]
+    fn fixtures_are_clean() {
+        assert!(messages(include_str!("../tests/fixtures/server.conf")).is_empty());
+        assert!(messages(include_str!("../tests/fixtures/client.conf")).is_empty());
+    }
+
+    #[test]
+    fn flags_broken_server() {
+        let lints = messages(include_str!("../tests/fixtures/broken.conf"));
+        assert_eq!(
+            lints,
+            [
+                "Unknown key 'DNSServer' in [Interface]",
+                "Interface PrivateKey is not a valid key",
+                "Peer #2 PublicKey is not a valid key",
+                "Peer #1 and Peer #3 have the same PublicKey",
+                "Peer #1 and Peer #3 share address 10.0.0.2",
+                "AllowedIPs overlap: Peer #2 10.0.1.0/24 and Peer #3 10.0.1.7/32",
+                "Peer #3 uses the interface address 10.0.0.1",
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_keys() {
+        assert!(is_valid_key("OGIWwcdNnOsX5IALWD8KopimXyC5cls8LEBHl3RRCTo="));
+        assert!(!is_valid_key(
+            "OGIWwcdNnOsX5IALWD8KopimXyC5cls8LEBHl3RRCTp="
+        ));
+        assert!(!is_valid_key("not-a-key"));
+    }
+}
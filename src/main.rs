@@ -1,40 +1,184 @@
-use std::process::Command;
+use std::{io::stdout, path::PathBuf, process::ExitCode};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use color_eyre::{Result, eyre::bail};
-use wg_tui::{App, check_dependencies};
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use wg_tui::{
+    App, Error, Settings, check_dependencies, helper_settings, load_tunnels, serve_exporter,
+    serve_helper, set_read_only, set_settings, start_helper, to_json, to_text,
+};
 
 #[derive(Parser)]
 #[command(version, about)]
-struct Cli {}
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
 
-const CMD_SUDO: &str = "sudo";
+    /// Show tunnels without allowing any change; runs without sudo
+    #[arg(long)]
+    read_only: bool,
 
-fn main() -> Result<()> {
-    Cli::parse();
+    /// Settings file to use instead of ~/.config/wg-tui/config.toml
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Directory holding the tunnel configs
+    #[arg(long, value_name = "DIR")]
+    config_dir: Option<String>,
+
+    /// Default DNS for new clients and peers
+    #[arg(long)]
+    dns: Option<String>,
+
+    /// Default ListenPort for new servers
+    #[arg(long, value_name = "PORT")]
+    listen_port: Option<String>,
+
+    /// PersistentKeepalive for new clients and peers (0 = off)
+    #[arg(long, value_name = "SECONDS")]
+    keepalive: Option<String>,
+
+    /// Color theme: dark, light or high-contrast
+    #[arg(long)]
+    theme: Option<String>,
+
+    /// Seconds between background refreshes (0 = off)
+    #[arg(long, value_name = "SECONDS")]
+    refresh_interval: Option<String>,
+
+    /// File the activity log is appended to
+    #[arg(long, value_name = "FILE")]
+    log_file: Option<String>,
+
+    /// Capture the mouse: on or off
+    #[arg(long, value_name = "on|off")]
+    mouse: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Print every tunnel with its live state and exit
+    Show {
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Serve Prometheus metrics of every tunnel
+    Exporter {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:9586")]
+        listen: String,
+    },
+    /// Serve privileged operations over stdin/stdout; started by the TUI
+    /// through sudo
+    #[command(hide = true)]
+    Helper,
+}
+
+impl Cli {
+    fn overrides(&self) -> [(&str, Option<&String>); 8] {
+        [
+            ("config_dir", self.config_dir.as_ref()),
+            ("dns", self.dns.as_ref()),
+            ("listen_port", self.listen_port.as_ref()),
+            ("keepalive", self.keepalive.as_ref()),
+            ("theme", self.theme.as_ref()),
+            ("refresh_interval", self.refresh_interval.as_ref()),
+            ("log_file", self.log_file.as_ref()),
+            ("mouse", self.mouse.as_ref()),
+        ]
+    }
+}
+
+/// Exits with [`Error::exit_code`] for errors of this crate and 1 otherwise.
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(report) => {
+            eprintln!("Error: {report:?}");
+            ExitCode::from(report.downcast_ref::<Error>().map_or(1, Error::exit_code))
+        }
+    }
+}
+
+fn run() -> Result<()> {
+    let cli = Cli::parse();
 
     color_eyre::install()?;
 
-    if !nix::unistd::geteuid().is_root() {
-        let exe = std::env::current_exe()?;
-        let args: Vec<_> = std::env::args().skip(1).collect();
-        let status = Command::new(CMD_SUDO).arg(exe).args(&args).status()?;
-        std::process::exit(status.code().unwrap_or(1));
+    // The helper runs as root, so it ignores the caller's settings files and
+    // refuses settings flags.
+    if let Some(Commands::Helper) = cli.command {
+        let from_caller =
+            cli.config.is_some() || cli.overrides().iter().any(|(_, value)| value.is_some());
+        set_settings(helper_settings(from_caller)?);
+        return Ok(serve_helper()?);
+    }
+
+    let mut settings = Settings::load(cli.config.as_deref())?;
+    for (key, value) in cli.overrides() {
+        if let Some(value) = value {
+            settings.set(key, value)?;
+        }
     }
 
+    // Reporting never changes anything.
+    if cli.read_only
+        || matches!(
+            cli.command,
+            Some(Commands::Show { .. } | Commands::Exporter { .. })
+        )
+    {
+        set_read_only();
+    }
     let missing = check_dependencies();
     if !missing.is_empty() {
         bail!("Missing required dependencies: {}", missing.join(", "));
     }
 
+    let mouse = settings.mouse;
+    set_settings(settings);
+    // Only the helper runs as root; the UI and reports stay unprivileged.
+    // --read-only gets by with the user's own privileges.
+    if !cli.read_only
+        && !nix::unistd::geteuid().is_root()
+        && let Err(e) = start_helper()
+    {
+        eprintln!("Could not start the privileged helper: {e}");
+    }
+
+    match cli.command {
+        Some(Commands::Show { json: true }) => {
+            println!("{}", to_json(&load_tunnels()));
+            return Ok(());
+        }
+        Some(Commands::Show { json: false }) => {
+            print!("{}", to_text(&load_tunnels()));
+            return Ok(());
+        }
+        Some(Commands::Exporter { listen }) => return Ok(serve_exporter(&listen)?),
+        _ => {}
+    }
+    // ratatui's panic hook restores the terminal but leaves mouse reporting
+    // on, which would fill the shell with escape codes.
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = crossterm::execute!(stdout(), DisableMouseCapture);
+        hook(info);
+    }));
     let mut terminal = ratatui::init();
+    if mouse {
+        crossterm::execute!(stdout(), EnableMouseCapture)?;
+    }
     let mut app = App::new();
 
     while !app.should_quit {
         terminal.draw(|f| app.draw(f))?;
         app.handle_events()?;
+        app.run_external_editor(&mut terminal)?;
     }
 
+    crossterm::execute!(stdout(), DisableMouseCapture)?;
     ratatui::restore();
     Ok(())
 }
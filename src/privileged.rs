@@ -0,0 +1,563 @@
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    settings::Settings,
+    wireguard::{CMD_WG, CMD_WG_QUICK, config_dir, validate_interface_name, wg_error},
+};
+
+const CMD_SUDO: &str = "sudo";
+
+/// The operations that need root: reading and writing files in the config
+/// directory and running `wg-quick` and `wg`. Files are plain names inside
+/// the config directory, such as `wg0.conf`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Request {
+    Ping,
+    List,
+    Read { file: String },
+    Exists { file: String },
+    Write { file: String, content: String },
+    Copy { from: String, to: String },
+    Rename { from: String, to: String },
+    Remove { file: String },
+    WgQuick { action: String, name: String },
+    WgShow { name: String },
+    WgSyncconf { name: String, content: String },
+}
+
+impl Request {
+    fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            Self::Write { .. }
+                | Self::Copy { .. }
+                | Self::Rename { .. }
+                | Self::Remove { .. }
+                | Self::WgQuick { .. }
+                | Self::WgSyncconf { .. }
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Reply {
+    Done,
+    Text(String),
+    Files(Vec<String>),
+    Flag(bool),
+    Failed(RemoteError),
+}
+
+/// [`Error`] as sent back by the helper.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RemoteError {
+    NotFound(String),
+    AlreadyExists(String),
+    InvalidField {
+        field: String,
+        reason: String,
+    },
+    CommandFailed {
+        cmd: String,
+        status: Option<i32>,
+        stderr: String,
+    },
+    PermissionDenied(String),
+    Io(String),
+    Other(String),
+}
+
+impl From<Error> for RemoteError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::NotFound(s) => Self::NotFound(s),
+            Error::AlreadyExists(s) => Self::AlreadyExists(s),
+            Error::InvalidField { field, reason } => Self::InvalidField { field, reason },
+            Error::CommandFailed {
+                cmd,
+                status,
+                stderr,
+            } => Self::CommandFailed {
+                cmd,
+                status,
+                stderr,
+            },
+            Error::PermissionDenied(s) => Self::PermissionDenied(s),
+            Error::Io(e) => Self::Io(e.to_string()),
+            Error::Zip(_) | Error::WgTui(_) => Self::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<RemoteError> for Error {
+    fn from(e: RemoteError) -> Self {
+        match e {
+            RemoteError::NotFound(s) => Self::NotFound(s),
+            RemoteError::AlreadyExists(s) => Self::AlreadyExists(s),
+            RemoteError::InvalidField { field, reason } => Self::InvalidField { field, reason },
+            RemoteError::CommandFailed {
+                cmd,
+                status,
+                stderr,
+            } => Self::CommandFailed {
+                cmd,
+                status,
+                stderr,
+            },
+            RemoteError::PermissionDenied(s) => Self::PermissionDenied(s),
+            RemoteError::Io(s) => Self::Io(io::Error::other(s)),
+            RemoteError::Other(s) => Self::WgTui(s),
+        }
+    }
+}
+
+/// Rejects anything but a `.conf` or `.conf.bak` file directly inside the
+/// config directory.
+fn check_file(file: &str) -> Result<(), Error> {
+    let valid = !file.starts_with('.')
+        && !file.contains('/')
+        && (file.ends_with(".conf") || file.ends_with(".conf.bak"));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::invalid(
+            "File",
+            format!("'{file}' is not a config file"),
+        ))
+    }
+}
+
+fn check_name(name: &str) -> Result<(), Error> {
+    validate_interface_name(name)?;
+    if name.starts_with('-') {
+        return Err(Error::invalid("Name", "cannot start with '-'"));
+    }
+    Ok(())
+}
+
+fn read_file(file: &str) -> Result<String, Error> {
+    fs::read_to_string(config_dir().join(file)).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => Error::NotFound(format!("Config '{file}'")),
+        _ => Error::from(e),
+    })
+}
+
+impl Request {
+    /// Performs the request in this process.
+    fn run(self) -> Result<Reply, Error> {
+        let dir = config_dir();
+        match self {
+            Self::Ping => Ok(Reply::Text(dir.display().to_string())),
+            Self::List => {
+                let mut files: Vec<String> = fs::read_dir(&dir)?
+                    .flatten()
+                    .filter_map(|e| e.file_name().into_string().ok())
+                    .filter(|f| f.ends_with(".conf"))
+                    .collect();
+                files.sort();
+                Ok(Reply::Files(files))
+            }
+            Self::Read { file } => {
+                check_file(&file)?;
+                read_file(&file).map(Reply::Text)
+            }
+            Self::Exists { file } => {
+                check_file(&file)?;
+                Ok(Reply::Flag(dir.join(file).exists()))
+            }
+            Self::Write { file, content } => {
+                check_file(&file)?;
+                fs::create_dir_all(&dir)?;
+                write_private(&dir.join(file), &content)?;
+                Ok(Reply::Done)
+            }
+            Self::Copy { from, to } => {
+                check_file(&from)?;
+                check_file(&to)?;
+                write_private(&dir.join(to), &read_file(&from)?)?;
+                Ok(Reply::Done)
+            }
+            Self::Rename { from, to } => {
+                check_file(&from)?;
+                check_file(&to)?;
+                fs::rename(dir.join(from), dir.join(to))?;
+                Ok(Reply::Done)
+            }
+            Self::Remove { file } => {
+                check_file(&file)?;
+                fs::remove_file(dir.join(file))?;
+                Ok(Reply::Done)
+            }
+            Self::WgQuick { action, name } => {
+                check_name(&name)?;
+                if !matches!(action.as_str(), "up" | "down") {
+                    return Err(Error::invalid("Action", "must be up or down"));
+                }
+                // A path, unlike a bare name, keeps wg-quick from looking
+                // the config up in /etc/wireguard.
+                let output = Command::new(CMD_WG_QUICK)
+                    .arg(&action)
+                    .arg(dir.join(format!("{name}.conf")))
+                    .output()?;
+                if !output.status.success() {
+                    return Err(wg_error(&output, &format!("wg-quick {action}")));
+                }
+                Ok(Reply::Done)
+            }
+            Self::WgShow { name } => {
+                check_name(&name)?;
+                let output = Command::new(CMD_WG).arg("show").arg(&name).output()?;
+                if !output.status.success() {
+                    return Err(wg_error(&output, "wg show"));
+                }
+                Ok(Reply::Text(String::from_utf8_lossy(&output.stdout).into()))
+            }
+            Self::WgSyncconf { name, content } => {
+                check_name(&name)?;
+                let mut child = Command::new(CMD_WG)
+                    .arg("syncconf")
+                    .arg(&name)
+                    .arg("/dev/stdin")
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .spawn()?;
+                if let Some(stdin) = child.stdin.as_mut() {
+                    stdin.write_all(content.as_bytes())?;
+                }
+                let output = child.wait_with_output()?;
+                if !output.status.success() {
+                    return Err(wg_error(&output, "wg syncconf"));
+                }
+                Ok(Reply::Done)
+            }
+        }
+    }
+}
+
+/// Writes `content` to `path`, readable by its owner only since configs hold
+/// private keys.
+fn write_private(path: &Path, content: &str) -> Result<(), Error> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+/// A `wg-tui helper` process running as root, talked to over its stdin and
+/// stdout with one JSON message per line.
+struct Helper {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    /// The config directory the helper works in.
+    config_dir: PathBuf,
+}
+
+impl Helper {
+    fn call(&mut self, request: &Request) -> Result<Reply, Error> {
+        let mut line = serde_json::to_string(request).map_err(json_error)?;
+        line.push('\n');
+        self.stdin.write_all(line.as_bytes())?;
+        self.stdin.flush()?;
+        let mut reply = String::new();
+        if self.stdout.read_line(&mut reply)? == 0 {
+            let _ = self.child.wait();
+            return Err(Error::WgTui("The privileged helper exited".into()));
+        }
+        serde_json::from_str(&reply).map_err(json_error)
+    }
+}
+
+fn json_error(e: serde_json::Error) -> Error {
+    Error::WgTui(format!("Invalid helper message: {e}"))
+}
+
+static HELPER: Mutex<Option<Helper>> = Mutex::new(None);
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Refuses every request that changes configs or interfaces from now on.
+pub fn set_read_only() {
+    READ_ONLY.store(true, Ordering::Relaxed);
+}
+
+pub fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// Settings of `wg-tui helper`. It acts as root for anyone allowed to run it
+/// through sudo, so only root-owned sources count: settings from the caller,
+/// such as a `--config-dir` naming another directory, are refused.
+pub fn helper_settings(from_caller: bool) -> Result<Settings, Error> {
+    if from_caller {
+        return Err(Error::PermissionDenied(
+            "The helper takes its settings only from /etc/wg-tui/config.toml".into(),
+        ));
+    }
+    Settings::system()
+}
+
+/// Starts `wg-tui helper` under `sudo`, which may ask for a password on the
+/// terminal, so this must run before the UI takes over the screen.
+pub fn start_helper() -> Result<(), Error> {
+    let exe = std::env::current_exe()?;
+    let mut child = Command::new(CMD_SUDO)
+        .arg(exe)
+        .arg("helper")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        return Err(Error::WgTui(
+            "Could not connect to the privileged helper".into(),
+        ));
+    };
+    let mut helper = Helper {
+        child,
+        stdin,
+        stdout: BufReader::new(stdout),
+        config_dir: PathBuf::new(),
+    };
+    match helper.call(&Request::Ping)? {
+        Reply::Text(dir) => helper.config_dir = PathBuf::from(dir),
+        reply => return Err(unexpected(reply)),
+    }
+    *HELPER.lock().unwrap_or_else(PoisonError::into_inner) = Some(helper);
+    Ok(())
+}
+
+pub fn has_helper() -> bool {
+    HELPER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .is_some()
+}
+
+/// The config directory of the running helper, which wins over the user's
+/// `config_dir` setting.
+pub fn helper_config_dir() -> Option<PathBuf> {
+    HELPER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+        .map(|h| h.config_dir.clone())
+}
+
+/// Whether privileged operations can succeed: this process is root or a
+/// helper is running.
+pub fn is_elevated() -> bool {
+    nix::unistd::geteuid().is_root() || has_helper()
+}
+
+/// Serves requests from stdin until it closes. This is `wg-tui helper`.
+pub fn serve() -> Result<(), Error> {
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let reply = match serde_json::from_str::<Request>(&line?) {
+            Ok(request) => request.run().unwrap_or_else(|e| Reply::Failed(e.into())),
+            Err(e) => Reply::Failed(RemoteError::Other(format!("Invalid request: {e}"))),
+        };
+        let mut line = serde_json::to_string(&reply).map_err(json_error)?;
+        line.push('\n');
+        stdout.write_all(line.as_bytes())?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+/// Sends `request` to the helper if one is running, or performs it here.
+fn call(request: Request) -> Result<Reply, Error> {
+    if is_read_only() && request.is_mutating() {
+        return Err(Error::PermissionDenied(
+            "wg-tui is in read-only mode".into(),
+        ));
+    }
+    let mut helper = HELPER.lock().unwrap_or_else(PoisonError::into_inner);
+    let reply = match helper.as_mut() {
+        Some(h) => match h.call(&request) {
+            Ok(reply) => reply,
+            Err(e) => {
+                *helper = None;
+                return Err(e);
+            }
+        },
+        None => {
+            drop(helper);
+            request.run()?
+        }
+    };
+    match reply {
+        Reply::Failed(e) => Err(e.into()),
+        reply => Ok(reply),
+    }
+}
+
+fn unexpected(reply: Reply) -> Error {
+    Error::WgTui(format!("Unexpected helper reply: {reply:?}"))
+}
+
+/// Paths of the `.conf` files in the config directory, sorted. The
+/// directory is the helper's when one runs, since that is where it listed
+/// them.
+pub fn list_configs() -> Result<Vec<PathBuf>, Error> {
+    match call(Request::List)? {
+        Reply::Files(files) => {
+            let dir = helper_config_dir().unwrap_or_else(config_dir);
+            Ok(files.iter().map(|file| dir.join(file)).collect())
+        }
+        reply => Err(unexpected(reply)),
+    }
+}
+
+pub fn read(file: &str) -> Result<String, Error> {
+    match call(Request::Read { file: file.into() })? {
+        Reply::Text(content) => Ok(content),
+        reply => Err(unexpected(reply)),
+    }
+}
+
+pub fn exists(file: &str) -> bool {
+    matches!(
+        call(Request::Exists { file: file.into() }),
+        Ok(Reply::Flag(true))
+    )
+}
+
+/// Writes `file`, creating the config directory if needed.
+pub fn write(file: &str, content: &str) -> Result<(), Error> {
+    call(Request::Write {
+        file: file.into(),
+        content: content.into(),
+    })
+    .map(drop)
+}
+
+pub fn copy(from: &str, to: &str) -> Result<(), Error> {
+    call(Request::Copy {
+        from: from.into(),
+        to: to.into(),
+    })
+    .map(drop)
+}
+
+pub fn rename(from: &str, to: &str) -> Result<(), Error> {
+    call(Request::Rename {
+        from: from.into(),
+        to: to.into(),
+    })
+    .map(drop)
+}
+
+pub fn remove(file: &str) -> Result<(), Error> {
+    call(Request::Remove { file: file.into() }).map(drop)
+}
+
+pub fn wg_quick(action: &str, name: &str) -> Result<(), Error> {
+    call(Request::WgQuick {
+        action: action.into(),
+        name: name.into(),
+    })
+    .map(drop)
+}
+
+/// Output of `wg show <name>`.
+pub fn wg_show(name: &str) -> Result<String, Error> {
+    match call(Request::WgShow { name: name.into() })? {
+        Reply::Text(output) => Ok(output),
+        reply => Err(unexpected(reply)),
+    }
+}
+
+/// Applies `content`, a config stripped of wg-quick keys, to a running
+/// interface.
+pub fn wg_syncconf(name: &str, content: &str) -> Result<(), Error> {
+    call(Request::WgSyncconf {
+        name: name.into(),
+        content: content.into(),
+    })
+    .map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_config_files_are_accepted() {
+        assert!(check_file("wg0.conf").is_ok());
+        assert!(check_file("wg0.conf.bak").is_ok());
+        assert!(check_file("../shadow.conf").is_err());
+        assert!(check_file(".conf").is_err());
+        assert!(check_file("passwd").is_err());
+        assert!(check_name("-h").is_err());
+        assert!(check_name("wg0").is_ok());
+    }
+
+    #[test]
+    fn helper_refuses_caller_settings() {
+        assert!(matches!(
+            helper_settings(true),
+            Err(Error::PermissionDenied(_))
+        ));
+        if let Ok(settings) = helper_settings(false) {
+            assert_eq!(settings, Settings::system().unwrap());
+        }
+    }
+
+    #[test]
+    fn classifies_mutating_requests() {
+        assert!(!Request::List.is_mutating());
+        assert!(!Request::WgShow { name: "wg0".into() }.is_mutating());
+        assert!(
+            Request::WgQuick {
+                action: "down".into(),
+                name: "wg0".into(),
+            }
+            .is_mutating()
+        );
+        assert!(
+            Request::Remove {
+                file: "wg0.conf".into()
+            }
+            .is_mutating()
+        );
+    }
+
+    #[test]
+    fn requests_and_errors_round_trip() {
+        let line = serde_json::to_string(&Request::WgQuick {
+            action: "up".into(),
+            name: "wg0".into(),
+        })
+        .unwrap();
+        assert_eq!(line, r#"{"op":"wg_quick","action":"up","name":"wg0"}"#);
+
+        let reply = Reply::Failed(RemoteError::from(Error::invalid("MTU", "too big")));
+        let reply: Reply = serde_json::from_str(&serde_json::to_string(&reply).unwrap()).unwrap();
+        let Reply::Failed(e) = reply else {
+            panic!("expected a failure");
+        };
+        assert_eq!(Error::from(e).field(), Some("MTU"));
+    }
+}
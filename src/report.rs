@@ -0,0 +1,319 @@
+use std::{
+    fmt::Write as _,
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    time::Duration,
+};
+
+use humansize::{BINARY, format_size};
+use serde::Serialize;
+
+use crate::{
+    error::Error,
+    types::{PeerInfo, Tunnel},
+    wireguard::try_load_tunnels,
+};
+
+#[derive(Serialize)]
+struct TunnelReport<'a> {
+    name: &'a str,
+    active: bool,
+    server: bool,
+    kill_switch: bool,
+    tags: &'a [String],
+    public_key: Option<&'a str>,
+    listen_port: Option<u16>,
+    peers: Vec<PeerReport<'a>>,
+}
+
+#[derive(Serialize)]
+struct PeerReport<'a> {
+    public_key: &'a str,
+    endpoint: Option<&'a str>,
+    allowed_ips: &'a [String],
+    /// Seconds since the latest handshake, `null` if there was none.
+    latest_handshake_age: Option<u64>,
+    rx_bytes: u64,
+    tx_bytes: u64,
+}
+
+impl<'a> TunnelReport<'a> {
+    fn new(tunnel: &'a Tunnel) -> Self {
+        let interface = tunnel.interface.as_ref();
+        Self {
+            name: &tunnel.name,
+            active: tunnel.is_active,
+            server: tunnel.is_server,
+            kill_switch: tunnel.kill_switch,
+            tags: &tunnel.tags,
+            public_key: interface.map(|i| i.public_key.as_str()),
+            listen_port: interface.and_then(|i| i.listen_port),
+            peers: interface
+                .map(|i| i.peers.iter().map(PeerReport::new).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl<'a> PeerReport<'a> {
+    fn new(peer: &'a PeerInfo) -> Self {
+        Self {
+            public_key: &peer.public_key,
+            endpoint: peer.endpoint.as_deref(),
+            allowed_ips: &peer.allowed_ips,
+            latest_handshake_age: peer.handshake_age.map(|age| age.as_secs()),
+            rx_bytes: peer.transfer_rx,
+            tx_bytes: peer.transfer_tx,
+        }
+    }
+}
+
+/// Every tunnel with its live state as a JSON array.
+pub fn to_json(tunnels: &[Tunnel]) -> String {
+    let reports: Vec<_> = tunnels.iter().map(TunnelReport::new).collect();
+    serde_json::to_string_pretty(&reports).expect("reports serialize")
+}
+
+/// One line per tunnel, for `wg-tui show` without `--json`.
+pub fn to_text(tunnels: &[Tunnel]) -> String {
+    let width = tunnels.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    let mut text = String::new();
+    for tunnel in tunnels {
+        let state = if tunnel.is_active { "up" } else { "down" };
+        let _ = write!(text, "{:<width$}  {state:<4}", tunnel.name);
+        if let Some(ref info) = tunnel.interface {
+            let rx: u64 = info.peers.iter().map(|p| p.transfer_rx).sum();
+            let tx: u64 = info.peers.iter().map(|p| p.transfer_tx).sum();
+            let _ = write!(
+                text,
+                "  {} peer(s)  rx {}  tx {}",
+                info.peers.len(),
+                format_size(rx, BINARY),
+                format_size(tx, BINARY)
+            );
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Escapes a Prometheus label value.
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Metrics in the Prometheus text format.
+pub fn to_prometheus(tunnels: &[Tunnel]) -> String {
+    let mut up = String::new();
+    let mut info = String::new();
+    let mut rx = String::new();
+    let mut tx = String::new();
+    let mut handshake = String::new();
+    for tunnel in tunnels {
+        let name = label(&tunnel.name);
+        let _ = writeln!(
+            up,
+            "wireguard_tunnel_up{{tunnel=\"{name}\"}} {}",
+            u8::from(tunnel.is_active)
+        );
+        let peers = tunnel.interface.iter().flat_map(|i| &i.peers);
+        for peer in peers {
+            let labels = format!(
+                "tunnel=\"{name}\",public_key=\"{}\"",
+                label(&peer.public_key)
+            );
+            let endpoint = label(peer.endpoint.as_deref().unwrap_or_default());
+            let _ = writeln!(
+                info,
+                "wireguard_peer_info{{{labels},endpoint=\"{endpoint}\",allowed_ips=\"{}\"}} 1",
+                label(&peer.allowed_ips.join(","))
+            );
+            let _ = writeln!(
+                rx,
+                "wireguard_peer_receive_bytes_total{{{labels}}} {}",
+                peer.transfer_rx
+            );
+            let _ = writeln!(
+                tx,
+                "wireguard_peer_transmit_bytes_total{{{labels}}} {}",
+                peer.transfer_tx
+            );
+            if let Some(age) = peer.handshake_age {
+                let _ = writeln!(
+                    handshake,
+                    "wireguard_peer_latest_handshake_age_seconds{{{labels}}} {}",
+                    age.as_secs()
+                );
+            }
+        }
+    }
+
+    let families = [
+        (
+            "wireguard_tunnel_up",
+            "gauge",
+            "Whether the tunnel interface exists",
+            up,
+        ),
+        (
+            "wireguard_peer_info",
+            "gauge",
+            "Endpoint and AllowedIPs of a peer",
+            info,
+        ),
+        (
+            "wireguard_peer_receive_bytes_total",
+            "counter",
+            "Bytes received from a peer",
+            rx,
+        ),
+        (
+            "wireguard_peer_transmit_bytes_total",
+            "counter",
+            "Bytes sent to a peer",
+            tx,
+        ),
+        (
+            "wireguard_peer_latest_handshake_age_seconds",
+            "gauge",
+            "Seconds since the latest handshake with a peer",
+            handshake,
+        ),
+    ];
+    let mut text = String::new();
+    for (metric, kind, help, samples) in families {
+        let _ = writeln!(text, "# HELP {metric} {help}\n# TYPE {metric} {kind}");
+        text.push_str(&samples);
+    }
+    text
+}
+
+/// Serves `/metrics` on `address` until the process is stopped, loading the
+/// tunnels anew for every scrape.
+pub fn serve_exporter(address: &str) -> Result<(), Error> {
+    let listener = TcpListener::bind(address)?;
+    eprintln!("Serving metrics on http://{address}/metrics");
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        // Requests are served one at a time, so a silent or stalled client
+        // must not hold up the next scrape.
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+        let _ = stream.set_write_timeout(Some(Duration::from_secs(5)));
+        let mut request_line = String::new();
+        if BufReader::new(&stream)
+            .read_line(&mut request_line)
+            .is_err()
+        {
+            continue;
+        }
+        let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+        let (status, body) = respond(path, try_load_tunnels);
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        // A scraper that hangs up early only loses its own response.
+        let _ = stream.write_all(response.as_bytes());
+    }
+    Ok(())
+}
+
+/// Status line and body for a request to `path`. A failed listing is an
+/// error rather than an empty scrape, so it cannot read as "no tunnels".
+fn respond(
+    path: &str,
+    load: impl FnOnce() -> Result<Vec<Tunnel>, Error>,
+) -> (&'static str, String) {
+    match path {
+        "/metrics" => match load() {
+            Ok(tunnels) => ("200 OK", to_prometheus(&tunnels)),
+            Err(e) => (
+                "500 Internal Server Error",
+                format!("Listing tunnels failed: {e}\n"),
+            ),
+        },
+        _ => (
+            "404 Not Found",
+            "Not found; metrics are at /metrics\n".into(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::InterfaceInfo;
+
+    fn tunnels() -> Vec<Tunnel> {
+        let peer = PeerInfo {
+            public_key: "pk=".into(),
+            endpoint: Some("203.0.113.1:51820".into()),
+            allowed_ips: vec!["10.0.0.2/32".into()],
+            handshake_age: Some(Duration::from_secs(42)),
+            transfer_rx: 1024,
+            transfer_tx: 2048,
+            ..Default::default()
+        };
+        vec![
+            Tunnel {
+                name: "wg0".into(),
+                is_active: true,
+                interface: Some(InterfaceInfo {
+                    public_key: "server=".into(),
+                    listen_port: Some(51820),
+                    peers: vec![peer],
+                }),
+                ..Default::default()
+            },
+            Tunnel {
+                name: "off\"ice".into(),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn writes_prometheus_metrics() {
+        let text = to_prometheus(&tunnels());
+        assert!(text.contains("wireguard_tunnel_up{tunnel=\"wg0\"} 1\n"));
+        assert!(text.contains("wireguard_tunnel_up{tunnel=\"off\\\"ice\"} 0\n"));
+        assert!(text.contains(
+            "wireguard_peer_receive_bytes_total{tunnel=\"wg0\",public_key=\"pk=\"} 1024\n"
+        ));
+        assert!(text.contains(
+            "wireguard_peer_latest_handshake_age_seconds{tunnel=\"wg0\",public_key=\"pk=\"} 42\n"
+        ));
+        assert!(text.contains("endpoint=\"203.0.113.1:51820\""));
+        assert!(text.contains("# TYPE wireguard_peer_transmit_bytes_total counter\n"));
+    }
+
+    #[test]
+    fn fails_scrapes_when_listing_fails() {
+        let (status, body) = respond("/metrics", || Ok(tunnels()));
+        assert_eq!(status, "200 OK");
+        assert!(body.contains("wireguard_tunnel_up"));
+
+        let (status, body) = respond("/metrics", || {
+            Err(Error::PermissionDenied("/etc/wireguard".into()))
+        });
+        assert_eq!(status, "500 Internal Server Error");
+        assert!(body.starts_with("Listing tunnels failed"));
+
+        assert_eq!(respond("/", || unreachable!()).0, "404 Not Found");
+    }
+
+    #[test]
+    fn writes_json() {
+        let json: serde_json::Value = serde_json::from_str(&to_json(&tunnels())).unwrap();
+        assert_eq!(json[0]["name"], "wg0");
+        assert_eq!(json[0]["peers"][0]["latest_handshake_age"], 42);
+        assert_eq!(json[0]["peers"][0]["tx_bytes"], 2048);
+        assert_eq!(json[1]["active"], false);
+        assert!(json[1]["public_key"].is_null());
+    }
+}
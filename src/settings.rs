@@ -0,0 +1,268 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{LazyLock, PoisonError, RwLock},
+};
+
+use toml::{Table, Value};
+
+use crate::{error::Error, theme::Theme};
+
+const SETTINGS_FILE: &str = "config.toml";
+const SYSTEM_SETTINGS: &str = "/etc/wg-tui/config.toml";
+
+/// Every setting with its description and whether it is stored as a number,
+/// in the order shown by the settings screen.
+pub const SETTINGS: &[(&str, &str, bool)] = &[
+    ("config_dir", "Directory holding the tunnel configs", false),
+    ("dns", "Default DNS for new clients and peers", false),
+    ("listen_port", "Default ListenPort for new servers", true),
+    (
+        "keepalive",
+        "PersistentKeepalive for new clients and peers (0 = off)",
+        true,
+    ),
+    ("theme", "Color theme: dark, light or high-contrast", false),
+    (
+        "refresh_interval",
+        "Seconds between background refreshes (0 = off)",
+        true,
+    ),
+    (
+        "log_file",
+        "File the activity log is appended to (empty = off)",
+        false,
+    ),
+    ("mouse", "Capture the mouse: on or off", false),
+];
+
+/// Defaults for new tunnels and the UI, merged from `/etc/wg-tui/config.toml`,
+/// the user's `config.toml` and command line flags, in that order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub config_dir: PathBuf,
+    pub dns: String,
+    pub listen_port: u16,
+    pub keepalive: u16,
+    pub theme: String,
+    pub refresh_interval: u64,
+    pub log_file: Option<PathBuf>,
+    pub mouse: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            config_dir: PathBuf::from("/etc/wireguard"),
+            dns: String::new(),
+            listen_port: 51820,
+            keepalive: 0,
+            theme: "dark".into(),
+            refresh_interval: 5,
+            log_file: None,
+            mouse: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the system file, then `file` or the user's `config.toml`.
+    /// Missing files are skipped.
+    pub fn load(file: Option<&Path>) -> Result<Self, Error> {
+        let mut settings = Self::default();
+        settings.merge_file(Path::new(SYSTEM_SETTINGS))?;
+        match file {
+            Some(path) if !path.exists() => {
+                return Err(Error::NotFound(path.display().to_string()));
+            }
+            Some(path) => settings.merge_file(path)?,
+            None => {
+                if let Some(path) = config_path(SETTINGS_FILE) {
+                    settings.merge_file(&path)?;
+                }
+            }
+        }
+        Ok(settings)
+    }
+
+    /// The defaults merged with `/etc/wg-tui/config.toml` only, which must be
+    /// owned by root and writable by nobody else. This is all the root helper
+    /// trusts.
+    pub fn system() -> Result<Self, Error> {
+        use std::os::unix::fs::MetadataExt;
+
+        let mut settings = Self::default();
+        let path = Path::new(SYSTEM_SETTINGS);
+        if let Ok(metadata) = fs::metadata(path)
+            && (metadata.uid() != 0 || metadata.mode() & 0o022 != 0)
+        {
+            return Err(Error::PermissionDenied(format!(
+                "{SYSTEM_SETTINGS} must be owned by root and not writable by others"
+            )));
+        }
+        settings.merge_file(path)?;
+        Ok(settings)
+    }
+
+    fn merge_file(&mut self, path: &Path) -> Result<(), Error> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let content = fs::read_to_string(path)?;
+        self.merge(&content)
+            .map_err(|e| Error::invalid(path.display().to_string(), e.to_string()))
+    }
+
+    /// Applies every `key = value` of a settings file.
+    pub fn merge(&mut self, content: &str) -> Result<(), Error> {
+        let table: Table = content
+            .parse()
+            .map_err(|e: toml::de::Error| Error::WgTui(e.message().to_string()))?;
+        for (key, value) in &table {
+            let value = match value {
+                Value::String(s) => s.clone(),
+                Value::Integer(i) => i.to_string(),
+                Value::Boolean(b) => b.to_string(),
+                _ => return Err(Error::invalid(key, "must be a string or number")),
+            };
+            self.set(key, &value)?;
+        }
+        Ok(())
+    }
+
+    /// The value of `key` as shown on the settings screen.
+    pub fn get(&self, key: &str) -> String {
+        match key {
+            "config_dir" => self.config_dir.display().to_string(),
+            "dns" => self.dns.clone(),
+            "listen_port" => self.listen_port.to_string(),
+            "keepalive" => self.keepalive.to_string(),
+            "theme" => self.theme.clone(),
+            "refresh_interval" => self.refresh_interval.to_string(),
+            "log_file" => self
+                .log_file
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+            "mouse" => if self.mouse { "on" } else { "off" }.into(),
+            _ => String::new(),
+        }
+    }
+
+    /// Parses and sets `key`, rejecting unknown keys and invalid values.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let value = value.trim();
+        let number = |max: u64| {
+            value
+                .parse::<u64>()
+                .ok()
+                .filter(|n| *n <= max)
+                .ok_or_else(|| Error::invalid(key, format!("must be a number up to {max}")))
+        };
+        match key {
+            "config_dir" if value.is_empty() => {
+                return Err(Error::invalid(key, "cannot be empty"));
+            }
+            "config_dir" => self.config_dir = PathBuf::from(value),
+            "dns" => self.dns = value.to_string(),
+            "listen_port" => self.listen_port = number(u64::from(u16::MAX))? as u16,
+            "keepalive" => self.keepalive = number(u64::from(u16::MAX))? as u16,
+            "theme" if Theme::builtin(value).is_none() => {
+                return Err(Error::invalid(key, format!("unknown theme '{value}'")));
+            }
+            "theme" => self.theme = value.to_string(),
+            "refresh_interval" => self.refresh_interval = number(3600)?,
+            "log_file" => self.log_file = (!value.is_empty()).then(|| PathBuf::from(value)),
+            "mouse" => {
+                self.mouse = match value.to_ascii_lowercase().as_str() {
+                    "on" | "true" | "yes" => true,
+                    "off" | "false" | "no" => false,
+                    _ => return Err(Error::invalid(key, "must be on or off")),
+                }
+            }
+            _ => return Err(Error::NotFound(format!("Setting '{key}'"))),
+        }
+        Ok(())
+    }
+
+    /// Writes `key` to the user's `config.toml`, keeping its other entries.
+    pub fn save_value(&self, key: &str) -> Result<PathBuf, Error> {
+        let path = config_path(SETTINGS_FILE)
+            .ok_or_else(|| Error::WgTui("Could not find a config directory".into()))?;
+        let mut table = match fs::read_to_string(&path) {
+            Ok(content) => content
+                .parse::<Table>()
+                .map_err(|e| Error::WgTui(format!("{}: {}", path.display(), e.message())))?,
+            Err(_) => Table::new(),
+        };
+        let value = self.get(key);
+        let numeric = SETTINGS.iter().any(|(k, _, n)| *k == key && *n);
+        let value = match value.parse::<i64>() {
+            Ok(n) if numeric => Value::Integer(n),
+            _ => Value::String(value),
+        };
+        table.insert(key.to_string(), value);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, table.to_string())?;
+        Ok(path)
+    }
+}
+
+static SETTINGS_STATE: LazyLock<RwLock<Settings>> =
+    LazyLock::new(|| RwLock::new(Settings::default()));
+
+/// The settings of this session.
+pub fn settings() -> Settings {
+    SETTINGS_STATE
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+pub fn set_settings(settings: Settings) {
+    *SETTINGS_STATE
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = settings;
+}
+
+/// Path of `file` in `$XDG_CONFIG_HOME/wg-tui`, falling back to `~/.config`.
+pub fn config_path(file: &str) -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(&std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join("wg-tui").join(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_and_validates_settings() {
+        let mut settings = Settings::default();
+        settings
+            .merge("config_dir = \"/srv/wg\"\nlisten_port = 51900\ntheme = \"light\"\n")
+            .unwrap();
+        assert_eq!(settings.config_dir, PathBuf::from("/srv/wg"));
+        assert_eq!(settings.listen_port, 51900);
+        assert_eq!(settings.get("theme"), "light");
+        assert_eq!(settings.get("keepalive"), "0");
+
+        assert!(settings.merge("listen_port = 70000").is_err());
+        assert!(settings.merge("theme = \"neon\"").is_err());
+        assert!(settings.merge("colour = \"red\"").is_err());
+        assert!(settings.merge("keepalive = true").is_err());
+        assert!(settings.set("keepalive", "25").is_ok());
+        assert_eq!(settings.keepalive, 25);
+
+        settings.merge("mouse = false").unwrap();
+        assert!(!settings.mouse);
+        assert_eq!(settings.get("mouse"), "off");
+        assert!(settings.set("mouse", "ON").is_ok());
+        assert!(settings.mouse);
+        assert!(settings.set("mouse", "maybe").is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{error::Error, filter::SortMode};
+
+const STATE_FILE: &str = "state";
+
+/// UI choices remembered between sessions, stored as `key = value` lines in
+/// `$XDG_STATE_HOME/wg-tui/state`.
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    pub sort: SortMode,
+}
+
+impl State {
+    /// Loads the saved state. A missing or unreadable file gives defaults.
+    pub fn load() -> Self {
+        state_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|content| Self::parse(&content))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let path =
+            state_path().ok_or_else(|| Error::WgTui("Could not find a state directory".into()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, format!("sort = {}\n", self.sort.label()))?;
+        Ok(())
+    }
+
+    fn parse(content: &str) -> Self {
+        let mut state = Self::default();
+        for line in content.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            if key.trim() == "sort"
+                && let Some(sort) = SortMode::from_label(value.trim())
+            {
+                state.sort = sort;
+            }
+        }
+        state
+    }
+}
+
+fn state_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_STATE_HOME").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(&std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(dir.join("wg-tui").join(STATE_FILE))
+}
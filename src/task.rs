@@ -0,0 +1,123 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::error::Error;
+
+const SPINNER: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_FRAME: Duration = Duration::from_millis(100);
+
+/// Set when the user cancels a task. Work that loops over several items
+/// checks it between items; a single command runs to completion and its
+/// result is still delivered.
+#[derive(Debug, Clone, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Work running on a worker thread so the UI keeps drawing.
+#[derive(Debug)]
+pub struct Task<T> {
+    pub label: String,
+    started: Instant,
+    rx: Receiver<T>,
+    cancel: CancelFlag,
+}
+
+impl<T: Send + 'static> Task<T> {
+    pub fn spawn(
+        label: impl Into<String>,
+        work: impl FnOnce(&CancelFlag) -> T + Send + 'static,
+    ) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let cancel = CancelFlag::default();
+        let flag = cancel.clone();
+        thread::spawn(move || tx.send(work(&flag)));
+        Self {
+            label: label.into(),
+            started: Instant::now(),
+            rx,
+            cancel,
+        }
+    }
+
+    /// The result once the work has finished.
+    pub fn poll(&self) -> Option<Result<T, Error>> {
+        match self.rx.try_recv() {
+            Ok(output) => Some(Ok(output)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(Error::WgTui(format!(
+                "{} failed unexpectedly",
+                self.label
+            )))),
+        }
+    }
+
+    /// Asks the work to stop. The task stays pending until the worker
+    /// reports back with whatever it got done.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn spinner(&self) -> char {
+        let frame = self.started.elapsed().as_millis() / SPINNER_FRAME.as_millis();
+        SPINNER[frame as usize % SPINNER.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait<T: Send + 'static>(task: &Task<T>) -> Result<T, Error> {
+        loop {
+            if let Some(result) = task.poll() {
+                return result;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn delivers_results_and_cancellation() {
+        let task = Task::spawn("sum", |_| 2 + 2);
+        assert_eq!(wait(&task).unwrap(), 4);
+
+        let (started_tx, started_rx) = mpsc::channel();
+        let task = Task::spawn("loop", move |cancel: &CancelFlag| {
+            started_tx.send(()).unwrap();
+            let mut rounds = 0;
+            while !cancel.is_cancelled() {
+                rounds += 1;
+                thread::sleep(Duration::from_millis(1));
+            }
+            rounds
+        });
+        started_rx.recv().unwrap();
+        task.cancel();
+        assert!(task.is_cancelled());
+        // The partial result still arrives after cancelling.
+        assert!(wait(&task).is_ok());
+
+        let task = Task::spawn("panics", |_| -> u8 { panic!("boom") });
+        assert!(wait(&task).is_err());
+    }
+}
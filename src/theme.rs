@@ -0,0 +1,214 @@
+use std::{
+    fs,
+    str::FromStr,
+    sync::{PoisonError, RwLock},
+};
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+use crate::{
+    error::Error,
+    settings::{config_path, settings},
+};
+
+const THEME_FILE: &str = "theme.toml";
+
+/// Colors used by every render function, by role rather than by hue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Popup borders, section headings and focused fields.
+    pub accent: Color,
+    /// Field labels, key hints, marks and warnings.
+    pub highlight: Color,
+    /// Regular text such as values and names.
+    pub text: Color,
+    /// Inactive tunnels, pane borders and hints.
+    pub muted: Color,
+    pub success: Color,
+    pub error: Color,
+    pub info: Color,
+    /// Secondary figures such as upload counters.
+    pub secondary: Color,
+    pub popup_bg: Color,
+    /// Background of the selected list row; `reset` uses reverse video.
+    pub selection: Color,
+}
+
+impl Theme {
+    pub const DARK: Self = Self {
+        accent: Color::Cyan,
+        highlight: Color::Yellow,
+        text: Color::White,
+        muted: Color::DarkGray,
+        success: Color::Green,
+        error: Color::Red,
+        info: Color::Blue,
+        secondary: Color::Magenta,
+        popup_bg: Color::Black,
+        selection: Color::DarkGray,
+    };
+
+    pub const LIGHT: Self = Self {
+        accent: Color::Blue,
+        highlight: Color::Magenta,
+        text: Color::Black,
+        muted: Color::DarkGray,
+        success: Color::Green,
+        error: Color::Red,
+        info: Color::Blue,
+        secondary: Color::Cyan,
+        popup_bg: Color::White,
+        selection: Color::Gray,
+    };
+
+    /// Bright colors on black, with a reverse-video selection that does not
+    /// depend on telling hues apart.
+    pub const HIGH_CONTRAST: Self = Self {
+        accent: Color::LightCyan,
+        highlight: Color::LightYellow,
+        text: Color::White,
+        muted: Color::Gray,
+        success: Color::LightGreen,
+        error: Color::LightRed,
+        info: Color::LightBlue,
+        secondary: Color::LightMagenta,
+        popup_bg: Color::Black,
+        selection: Color::Reset,
+    };
+
+    /// The terminal's own colors, used when `NO_COLOR` is set.
+    pub const PLAIN: Self = Self {
+        accent: Color::Reset,
+        highlight: Color::Reset,
+        text: Color::Reset,
+        muted: Color::Reset,
+        success: Color::Reset,
+        error: Color::Reset,
+        info: Color::Reset,
+        secondary: Color::Reset,
+        popup_bg: Color::Reset,
+        selection: Color::Reset,
+    };
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::DARK),
+            "light" => Some(Self::LIGHT),
+            "high-contrast" => Some(Self::HIGH_CONTRAST),
+            _ => None,
+        }
+    }
+
+    /// Style of the selected list row.
+    pub fn selected(&self) -> Style {
+        let style = Style::default().add_modifier(Modifier::BOLD);
+        if self.selection == Color::Reset {
+            style.add_modifier(Modifier::REVERSED)
+        } else {
+            style.bg(self.selection)
+        }
+    }
+
+    /// Background of popups drawn over the main view.
+    pub fn popup(&self) -> Style {
+        Style::default().bg(self.popup_bg)
+    }
+
+    /// Parses a theme file: an optional `base` theme name, defaulting to
+    /// `default_base`, and any color overrides as names (`lightblue`), indexes
+    /// (`208`) or hex (`#005f87`).
+    pub fn parse(content: &str, default_base: &str) -> Result<Self, Error> {
+        let file: ThemeFile =
+            toml::from_str(content).map_err(|e| Error::WgTui(e.message().to_string()))?;
+        let base = file.base.as_deref().unwrap_or(default_base);
+        let mut theme = Self::builtin(base)
+            .ok_or_else(|| Error::WgTui(format!("Unknown base theme '{base}'")))?;
+        let overrides = [
+            (&mut theme.accent, file.accent),
+            (&mut theme.highlight, file.highlight),
+            (&mut theme.text, file.text),
+            (&mut theme.muted, file.muted),
+            (&mut theme.success, file.success),
+            (&mut theme.error, file.error),
+            (&mut theme.info, file.info),
+            (&mut theme.secondary, file.secondary),
+            (&mut theme.popup_bg, file.popup_bg),
+            (&mut theme.selection, file.selection),
+        ];
+        for (color, value) in overrides {
+            if let Some(value) = value {
+                *color = Color::from_str(&value)
+                    .map_err(|_| Error::WgTui(format!("Invalid color '{value}'")))?;
+            }
+        }
+        Ok(theme)
+    }
+
+    /// The theme for this session: plain when `NO_COLOR` is set, otherwise
+    /// `theme.toml` from the config directory over the `theme` setting.
+    pub fn load() -> Result<Self, Error> {
+        if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return Ok(Self::PLAIN);
+        }
+        let base = settings().theme;
+        let Some(path) = config_path(THEME_FILE).filter(|p| p.exists()) else {
+            return Ok(Self::builtin(&base).unwrap_or(Self::DARK));
+        };
+        let content = fs::read_to_string(&path)?;
+        Self::parse(&content, &base).map_err(|e| Error::WgTui(format!("{}: {e}", path.display())))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    base: Option<String>,
+    accent: Option<String>,
+    highlight: Option<String>,
+    text: Option<String>,
+    muted: Option<String>,
+    success: Option<String>,
+    error: Option<String>,
+    info: Option<String>,
+    secondary: Option<String>,
+    popup_bg: Option<String>,
+    selection: Option<String>,
+}
+
+static THEME: RwLock<Theme> = RwLock::new(Theme::DARK);
+
+/// The active theme.
+pub fn theme() -> Theme {
+    *THEME.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub fn set_theme(theme: Theme) {
+    *THEME.write().unwrap_or_else(PoisonError::into_inner) = theme;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_theme_file() {
+        let theme = Theme::parse(
+            "base = \"light\"\naccent = \"#005f87\"\nmuted = \"244\"\n",
+            "dark",
+        )
+        .unwrap();
+        assert_eq!(theme.accent, Color::Rgb(0x00, 0x5f, 0x87));
+        assert_eq!(theme.muted, Color::Indexed(244));
+        assert_eq!(theme.text, Theme::LIGHT.text);
+
+        assert_eq!(Theme::parse("", "dark").unwrap(), Theme::DARK);
+        assert_eq!(
+            Theme::parse("", "high-contrast").unwrap(),
+            Theme::HIGH_CONTRAST
+        );
+        assert!(Theme::parse("base = \"solarized\"", "dark").is_err());
+        assert!(Theme::parse("accent = \"not-a-color\"", "dark").is_err());
+        assert!(Theme::parse("border = \"red\"", "dark").is_err());
+    }
+}
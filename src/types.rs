@@ -1,6 +1,10 @@
-use std::path::PathBuf;
+use std::{net::IpAddr, path::PathBuf, time::Duration};
 
-use ratatui::style::{Color, Style};
+use ratatui::style::Style;
+
+use crate::theme::theme;
+
+use crate::{cidr::Cidr, lint::Lint};
 
 #[derive(Debug, Clone, Default)]
 pub struct Tunnel {
@@ -8,6 +12,31 @@ pub struct Tunnel {
     pub config_path: PathBuf,
     pub is_active: bool,
     pub interface: Option<InterfaceInfo>,
+    pub lints: Vec<Lint>,
+    pub kill_switch: bool,
+    /// True if the config has a ListenPort, i.e. it accepts connections.
+    pub is_server: bool,
+    pub endpoints: Vec<String>,
+    /// Peer names from `# Name = ...` comments in the config.
+    pub peer_names: Vec<String>,
+    /// Tags from the leading `# Tags = ...` comment in the config.
+    pub tags: Vec<String>,
+}
+
+/// WireGuard re-handshakes every two minutes while traffic flows, so a
+/// tunnel without a handshake for longer than this is likely dead.
+pub const STALE_HANDSHAKE: Duration = Duration::from_secs(180);
+
+impl Tunnel {
+    /// An active tunnel none of whose peers completed a recent handshake.
+    pub fn is_stale(&self) -> bool {
+        self.is_active
+            && !self.interface.as_ref().is_some_and(|i| {
+                i.peers
+                    .iter()
+                    .any(|p| p.handshake_age.is_some_and(|age| age <= STALE_HANDSHAKE))
+            })
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -23,6 +52,7 @@ pub struct PeerInfo {
     pub endpoint: Option<String>,
     pub allowed_ips: Vec<String>,
     pub latest_handshake: Option<String>,
+    pub handshake_age: Option<Duration>,
     pub transfer_rx: u64,
     pub transfer_tx: u64,
 }
@@ -36,6 +66,7 @@ pub struct NewTunnelDraft {
     pub peer_public_key: String,
     pub allowed_ips: String,
     pub endpoint: String,
+    pub kill_switch: String,
 }
 
 #[derive(Debug, Clone)]
@@ -47,16 +78,105 @@ pub struct NewServerDraft {
     pub egress_interface: String,
 }
 
+/// Outcome of one tunnel in a bulk action.
 #[derive(Debug, Clone)]
-pub struct EditTunnelDraft {
+pub struct BulkResult {
     pub name: String,
-    pub address: String,
-    pub dns: String,
+    pub error: Option<String>,
+}
+
+/// One line of the diagnostics checklist.
+#[derive(Debug, Clone)]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+/// Outcome of a path MTU probe; with several peers, the most limiting one.
+#[derive(Debug, Clone)]
+pub struct MtuProbe {
+    pub endpoint: IpAddr,
+    /// Largest packet that reached the endpoint without fragmentation.
+    pub path_mtu: u16,
+    /// Bytes WireGuard adds to each packet on this path.
+    pub overhead: u16,
+    pub recommended: u16,
+    /// The config's `MTU`, if set.
+    pub current: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CloneTunnelDraft {
+    pub source: String,
+    pub name: String,
+    pub fresh_keys: String,
     pub listen_port: String,
-    pub mtu: String,
-    pub peer_endpoint: String,
-    pub peer_allowed_ips: String,
-    pub peer_persistent_keepalive: String,
+}
+
+/// `[Interface]` keys exposed by the edit form, in display order.
+pub const EDIT_INTERFACE_KEYS: &[&str] = &[
+    "Address",
+    "DNS",
+    "ListenPort",
+    "MTU",
+    "Table",
+    "FwMark",
+    "PreUp",
+    "PostUp",
+    "PreDown",
+    "PostDown",
+];
+
+/// `[Peer]` keys exposed by the edit form, in display order.
+pub const EDIT_PEER_KEYS: &[&str] = &[
+    "PublicKey",
+    "PresharedKey",
+    "Endpoint",
+    "AllowedIPs",
+    "PersistentKeepalive",
+];
+
+/// Editable values of a tunnel config. An empty value means the key is
+/// absent from the file.
+#[derive(Debug, Clone)]
+pub struct EditTunnelDraft {
+    pub name: String,
+    /// Values for [`EDIT_INTERFACE_KEYS`].
+    pub interface: Vec<String>,
+    pub peers: Vec<PeerDraft>,
+    pub kill_switch: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct PeerDraft {
+    /// Values for [`EDIT_PEER_KEYS`].
+    pub values: Vec<String>,
+    /// Position of the `[Peer]` section this was read from; `None` for a
+    /// peer added in the form.
+    pub source: Option<usize>,
+}
+
+impl PeerDraft {
+    pub fn new() -> Self {
+        Self {
+            values: vec![String::new(); EDIT_PEER_KEYS.len()],
+            source: None,
+        }
+    }
+
+    pub fn value(&self, key: &str) -> &str {
+        EDIT_PEER_KEYS
+            .iter()
+            .position(|k| *k == key)
+            .map_or("", |i| &self.values[i])
+    }
+}
+
+impl Default for PeerDraft {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -66,7 +186,22 @@ pub struct PeerConfig {
     pub listen_port: u16,
 }
 
-#[derive(Clone)]
+/// An AllowedIPs prefix of a tunnel that overlaps a prefix already routed
+/// elsewhere on the host.
+#[derive(Debug, Clone)]
+pub struct RouteConflict {
+    pub prefix: Cidr,
+    pub existing: Cidr,
+    pub source: RouteSource,
+}
+
+#[derive(Debug, Clone)]
+pub enum RouteSource {
+    Tunnel(String),
+    Route { dev: Option<String> },
+}
+
+#[derive(Debug, Clone)]
 pub enum Message {
     Info(String),
     Success(String),
@@ -76,9 +211,9 @@ pub enum Message {
 impl Message {
     pub fn style(&self) -> Style {
         Style::default().fg(match self {
-            Self::Info(_) => Color::Blue,
-            Self::Success(_) => Color::Green,
-            Self::Error(_) => Color::Red,
+            Self::Info(_) => theme().info,
+            Self::Success(_) => theme().success,
+            Self::Error(_) => theme().error,
         })
     }
 
@@ -1,19 +1,38 @@
-use crate::app::EditFormState;
-use crate::types::PeerInfo;
-use humansize::{format_size, BINARY};
-use qrcode::{render::unicode, QrCode};
+use crate::activity::{LogEntry, format_timestamp, level};
+use crate::app::{EditFormState, KILL_SWITCH_FIELD, PEER_FIELD_START, RawEditorState};
+use crate::config::WgConfig;
+use crate::lint::{Lint, Severity, lint_config};
+use crate::settings::{SETTINGS, Settings};
+use crate::theme::theme;
+use crate::types::{
+    BulkResult, DiagnosticCheck, EDIT_INTERFACE_KEYS, EDIT_PEER_KEYS, Message, MtuProbe, PeerInfo,
+    RouteConflict, RouteSource,
+};
+use humansize::{BINARY, format_size};
+use qrcode::{QrCode, render::unicode};
 use ratatui::{
+    Frame,
     layout::{Alignment, Constraint, Layout, Rect},
-    style::{Color, Style, Stylize},
-    text::{Line, Text},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
     widgets::{Block, Borders, Clear, Paragraph, Wrap},
-    Frame,
 };
+use std::collections::VecDeque;
+use tui_input::Input;
+
+/// Columns kept visible around the cursor of a focused edit field.
+const EDIT_VALUE_WIDTH: usize = 60;
+
+/// Keys whose values the raw editor masks until revealed.
+const SECRET_KEYS: &[&str] = &["PrivateKey", "PresharedKey"];
+
+/// Width of the line number gutter in the raw editor.
+const GUTTER_WIDTH: u16 = 5;
 
 pub fn bordered_block(title: Option<&str>) -> Block<'_> {
     let block = Block::default()
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::DarkGray));
+        .border_style(Style::default().fg(theme().muted));
     match title {
         Some(t) => block.title(t),
         None => block,
@@ -22,13 +41,13 @@ pub fn bordered_block(title: Option<&str>) -> Block<'_> {
 
 pub fn label(key: &str, val: &str) -> Line<'static> {
     Line::from(vec![
-        key.to_string().fg(Color::Yellow),
+        key.to_string().fg(theme().highlight),
         val.to_string().into(),
     ])
 }
 
 pub fn section(title: &str) -> Line<'static> {
-    Line::from(format!("── {title} ──").fg(Color::Cyan))
+    Line::from(format!("── {title} ──").fg(theme().accent))
 }
 
 pub fn peer_lines(peer: &PeerInfo) -> Vec<Line<'static>> {
@@ -45,33 +64,52 @@ pub fn peer_lines(peer: &PeerInfo) -> Vec<Line<'static>> {
     }
     if peer.transfer_rx > 0 || peer.transfer_tx > 0 {
         lines.push(Line::from(vec![
-            "  Transfer: ".to_string().fg(Color::Yellow),
-            "↓ ".fg(Color::Green),
+            "  Transfer: ".to_string().fg(theme().highlight),
+            "↓ ".fg(theme().success),
             format_bytes(peer.transfer_rx).into(),
             "  ".into(),
-            "↑ ".fg(Color::Magenta),
+            "↑ ".fg(theme().secondary),
             format_bytes(peer.transfer_tx).into(),
         ]));
     }
     lines
 }
 
-pub fn render_confirm(f: &mut Frame, name: &str) {
-    let area = centered_rect(40, 20, f.area());
+pub fn lint_line(lint: &Lint) -> Line<'static> {
+    let (icon, color) = match lint.severity {
+        Severity::Error => ("✗ ", theme().error),
+        Severity::Warning => ("! ", theme().highlight),
+    };
+    Line::from(vec![
+        format!("  {icon}").fg(color),
+        lint.message.clone().into(),
+    ])
+}
+
+pub fn render_confirm(f: &mut Frame, names: &[String]) {
+    let height = if names.len() > 1 { 50 } else { 20 };
+    let area = centered_rect(40, height, f.area());
     f.render_widget(Clear, area);
 
-    let lines = vec![
-        Line::from("Delete tunnel?".fg(Color::Red).bold()),
-        Line::raw(""),
-        Line::from(format!("'{name}'").fg(Color::Yellow)),
+    let title = match names.len() {
+        1 => "Delete tunnel?".to_string(),
+        n => format!("Delete {n} tunnels?"),
+    };
+    let mut lines = vec![Line::from(title.fg(theme().error).bold()), Line::raw("")];
+    lines.extend(
+        names
+            .iter()
+            .map(|name| Line::from(format!("'{name}'").fg(theme().highlight))),
+    );
+    lines.extend([
         Line::raw(""),
         Line::from(vec![
-            "y".fg(Color::Green).bold(),
+            "y".fg(theme().success).bold(),
             " to confirm, ".into(),
-            "any key".fg(Color::Yellow),
+            "any key".fg(theme().highlight),
             " to cancel".into(),
         ]),
-    ];
+    ]);
 
     f.render_widget(
         Paragraph::new(Text::from(lines))
@@ -79,34 +117,214 @@ pub fn render_confirm(f: &mut Frame, name: &str) {
                 Block::default()
                     .title(" Confirm ")
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Red)),
+                    .border_style(Style::default().fg(theme().error)),
             )
-            .style(Style::default().bg(Color::Black))
+            .style(theme().popup())
             .alignment(ratatui::layout::Alignment::Center),
         area,
     );
 }
 
-pub fn render_full_tunnel_warning(f: &mut Frame, name: &str) {
-    let area = centered_rect(60, 30, f.area());
+pub fn render_bulk_results(f: &mut Frame, title: &str, results: &[BulkResult]) {
+    let area = centered_rect(60, 50, f.area());
     f.render_widget(Clear, area);
 
-    let lines = vec![
-        Line::from("Full-tunnel warning".fg(Color::Yellow).bold()),
-        Line::raw(""),
-        Line::from(format!("'{name}'").fg(Color::Cyan)),
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    let summary = format!("{} succeeded, {failed} failed", results.len() - failed);
+    let mut lines = vec![
+        Line::from(summary.fg(if failed == 0 {
+            theme().success
+        } else {
+            theme().error
+        })),
         Line::raw(""),
-        Line::from("AllowedIPs includes a default route.".fg(Color::White)),
-        Line::from("If you're connected via SSH, enabling this".fg(Color::White)),
-        Line::from("may lock you out of the server.".fg(Color::White)),
+    ];
+    lines.extend(results.iter().map(|r| match &r.error {
+        None => Line::from(vec!["  ✓ ".fg(theme().success), r.name.clone().into()]),
+        Some(e) => Line::from(vec![
+            "  ✗ ".fg(theme().error),
+            format!("{}: ", r.name).into(),
+            e.clone().fg(theme().error),
+        ]),
+    }));
+    lines.push(Line::raw(""));
+    lines.push(Line::from(
+        "Press any key to close".fg(theme().muted).italic(),
+    ));
+
+    f.render_widget(
+        Paragraph::new(Text::from(lines))
+            .block(
+                Block::default()
+                    .title(format!(" {title} "))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme().accent)),
+            )
+            .style(theme().popup())
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+pub fn render_diagnostics(f: &mut Frame, name: &str, checks: &[DiagnosticCheck]) {
+    let area = centered_rect(70, 70, f.area());
+    f.render_widget(Clear, area);
+
+    let failed = checks.iter().filter(|c| !c.passed).count();
+    let summary = format!("{} passed, {failed} failed", checks.len() - failed);
+    let mut lines = vec![
+        Line::from(summary.fg(if failed == 0 {
+            theme().success
+        } else {
+            theme().error
+        })),
         Line::raw(""),
+    ];
+    lines.extend(checks.iter().map(|c| {
+        let (mark, color) = if c.passed {
+            ("  ✓ ", theme().success)
+        } else {
+            ("  ✗ ", theme().error)
+        };
         Line::from(vec![
-            "y".fg(Color::Green).bold(),
-            " to enable anyway, ".into(),
-            "any key".fg(Color::Yellow),
-            " to cancel".into(),
+            mark.fg(color),
+            format!("{}: ", c.name).into(),
+            c.detail
+                .clone()
+                .fg(if c.passed { theme().muted } else { color }),
+        ])
+    }));
+    lines.push(Line::raw(""));
+    lines.push(Line::from(
+        "Press any key to close".fg(theme().muted).italic(),
+    ));
+
+    f.render_widget(
+        Paragraph::new(Text::from(lines))
+            .block(
+                Block::default()
+                    .title(format!(" Diagnostics: {name} "))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme().accent)),
+            )
+            .style(theme().popup())
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+pub fn render_mtu_probe(f: &mut Frame, name: &str, probe: &MtuProbe, can_apply: bool) {
+    let area = centered_rect(60, 40, f.area());
+    f.render_widget(Clear, area);
+
+    let family = if probe.endpoint.is_ipv4() {
+        "IPv4"
+    } else {
+        "IPv6"
+    };
+    let mut lines = vec![
+        label("Endpoint:      ", &probe.endpoint.to_string()),
+        label("Path MTU:      ", &probe.path_mtu.to_string()),
+        label(
+            "Overhead:      ",
+            &format!("{} bytes (WireGuard over {family})", probe.overhead),
+        ),
+        label(
+            "Current MTU:   ",
+            probe
+                .current
+                .as_deref()
+                .unwrap_or("not set (wg-quick picks one)"),
+        ),
+        Line::from(vec![
+            "Recommended:   ".fg(theme().highlight),
+            probe.recommended.to_string().fg(theme().success).bold(),
         ]),
+        Line::raw(""),
     ];
+    lines.push(if can_apply {
+        Line::from(vec![
+            "Enter".fg(theme().success).bold(),
+            format!(" to set MTU = {}, ", probe.recommended).into(),
+            "any key".fg(theme().highlight),
+            " to close".into(),
+        ])
+    } else {
+        Line::from("Press any key to close".fg(theme().muted).italic())
+    });
+
+    f.render_widget(
+        Paragraph::new(Text::from(lines))
+            .block(
+                Block::default()
+                    .title(format!(" Path MTU: {name} "))
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme().accent)),
+            )
+            .style(theme().popup())
+            .wrap(Wrap { trim: false }),
+        area,
+    );
+}
+
+/// Warns before bringing tunnels up whose AllowedIPs overlap routes already in
+/// use or include a default route.
+pub fn render_up_warning(
+    f: &mut Frame,
+    conflicts: &[(String, Vec<RouteConflict>)],
+    full_tunnel: &[String],
+) {
+    let area = centered_rect(70, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let mut lines = vec![];
+    if !conflicts.is_empty() {
+        lines.push(Line::from("Route conflict".fg(theme().highlight).bold()));
+        lines.push(Line::from(
+            "AllowedIPs overlap routes already in use:".fg(theme().text),
+        ));
+        for (name, conflicts) in conflicts {
+            lines.push(Line::raw(""));
+            lines.push(Line::from(format!("'{name}'").fg(theme().accent)));
+            lines.extend(conflicts.iter().map(|c| {
+                let source = match &c.source {
+                    RouteSource::Tunnel(t) => format!("tunnel '{t}'"),
+                    RouteSource::Route { dev: Some(dev) } => format!("route via {dev}"),
+                    RouteSource::Route { dev: None } => "host route".into(),
+                };
+                Line::from(vec![
+                    c.prefix.to_string().fg(theme().highlight),
+                    " ↔ ".into(),
+                    c.existing.to_string().fg(theme().highlight),
+                    format!("  ({source})").fg(theme().muted),
+                ])
+            }));
+        }
+        lines.push(Line::raw(""));
+    }
+    if !full_tunnel.is_empty() {
+        lines.push(Line::from(
+            "Full-tunnel warning".fg(theme().highlight).bold(),
+        ));
+        let names: Vec<_> = full_tunnel.iter().map(|n| format!("'{n}'")).collect();
+        lines.push(Line::from(names.join(", ").fg(theme().accent)));
+        lines.push(Line::from(
+            "AllowedIPs includes a default route.".fg(theme().text),
+        ));
+        lines.push(Line::from(
+            "If you're connected via SSH, enabling this".fg(theme().text),
+        ));
+        lines.push(Line::from(
+            "may lock you out of the server.".fg(theme().text),
+        ));
+        lines.push(Line::raw(""));
+    }
+    lines.push(Line::from(vec![
+        "y".fg(theme().success).bold(),
+        " to enable anyway, ".into(),
+        "any key".fg(theme().highlight),
+        " to cancel".into(),
+    ]));
 
     f.render_widget(
         Paragraph::new(Text::from(lines))
@@ -114,10 +332,11 @@ pub fn render_full_tunnel_warning(f: &mut Frame, name: &str) {
                 Block::default()
                     .title(" Warning ")
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Yellow)),
+                    .border_style(Style::default().fg(theme().highlight)),
             )
-            .style(Style::default().bg(Color::Black))
-            .alignment(ratatui::layout::Alignment::Center),
+            .style(theme().popup())
+            .alignment(ratatui::layout::Alignment::Center)
+            .wrap(Wrap { trim: false }),
         area,
     );
 }
@@ -127,29 +346,29 @@ pub fn render_add_menu(f: &mut Frame) {
     f.render_widget(Clear, area);
 
     let lines = vec![
-        Line::from("Add Tunnel".fg(Color::Cyan).bold()),
+        Line::from("Add Tunnel".fg(theme().accent).bold()),
         Line::raw(""),
         Line::from(vec![
-            "i".fg(Color::Yellow).bold(),
+            "i".fg(theme().highlight).bold(),
             " / ".into(),
-            "1".fg(Color::Yellow).bold(),
+            "1".fg(theme().highlight).bold(),
             "  Import from file".into(),
         ]),
         Line::from(vec![
-            "c".fg(Color::Yellow).bold(),
+            "c".fg(theme().highlight).bold(),
             " / ".into(),
-            "2".fg(Color::Yellow).bold(),
+            "2".fg(theme().highlight).bold(),
             "  Create client".into(),
         ]),
         Line::from(vec![
-            "s".fg(Color::Yellow).bold(),
+            "s".fg(theme().highlight).bold(),
             " / ".into(),
-            "3".fg(Color::Yellow).bold(),
+            "3".fg(theme().highlight).bold(),
             "  Create server".into(),
         ]),
         Line::raw(""),
-        Line::from("Esc".fg(Color::DarkGray).italic()),
-        Line::from("to cancel".fg(Color::DarkGray).italic()),
+        Line::from("Esc".fg(theme().muted).italic()),
+        Line::from("to cancel".fg(theme().muted).italic()),
     ];
 
     f.render_widget(
@@ -158,9 +377,9 @@ pub fn render_add_menu(f: &mut Frame) {
                 Block::default()
                     .title(" Add ")
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan)),
+                    .border_style(Style::default().fg(theme().accent)),
             )
-            .style(Style::default().bg(Color::Black))
+            .style(theme().popup())
             .alignment(ratatui::layout::Alignment::Center),
         area,
     );
@@ -171,21 +390,21 @@ pub fn render_input(f: &mut Frame, title: &str, prompt: &str, value: &str, hint:
     f.render_widget(Clear, area);
 
     let mut lines = vec![
-        Line::from(prompt.fg(Color::Yellow)),
+        Line::from(prompt.fg(theme().highlight)),
         Line::raw(""),
         Line::from(format!("{value}█")),
         Line::raw(""),
     ];
 
     if let Some(h) = hint {
-        lines.push(Line::from(h.fg(Color::DarkGray).italic()));
+        lines.push(Line::from(h.fg(theme().muted).italic()));
         lines.push(Line::raw(""));
     }
 
     lines.push(Line::from(vec![
-        "Enter".fg(Color::Green).bold(),
+        "Enter".fg(theme().success).bold(),
         " confirm  ".into(),
-        "Esc".fg(Color::Yellow),
+        "Esc".fg(theme().highlight),
         " cancel".into(),
     ]));
 
@@ -195,9 +414,114 @@ pub fn render_input(f: &mut Frame, title: &str, prompt: &str, value: &str, hint:
                 Block::default()
                     .title(format!(" {title} "))
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan)),
+                    .border_style(Style::default().fg(theme().accent)),
+            )
+            .style(theme().popup()),
+        area,
+    );
+}
+
+/// Renders the activity log, newest entry at the bottom, `scroll` entries up
+/// from the newest.
+pub fn render_activity_log(f: &mut Frame, entries: &VecDeque<LogEntry>, scroll: usize) {
+    let area = centered_rect(85, 80, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Activity Log ")
+        .title_bottom(Line::from(" j/k scroll  g/G oldest/newest  Esc close ").fg(theme().muted))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme().accent));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    // Fill the popup from the bottom so the newest entries stay in view.
+    let mut lines: Vec<Line> = Vec::new();
+    for entry in entries.iter().rev().skip(scroll) {
+        let text = entry.message.text();
+        let mut text_lines = text.trim_end().lines();
+        let mut entry_lines = vec![Line::from(vec![
+            format_timestamp(entry.time).fg(theme().muted),
+            Span::styled(
+                format!(" {:<5} ", level(&entry.message)),
+                entry.message.style(),
+            ),
+            text_lines.next().unwrap_or_default().to_string().into(),
+        ])];
+        entry_lines.extend(text_lines.map(|line| Line::from(format!("    {line}"))));
+        lines.splice(0..0, entry_lines);
+        if lines.len() >= usize::from(inner.height) {
+            break;
+        }
+    }
+    if lines.is_empty() {
+        lines.push(Line::from(" No activity yet".fg(theme().muted)));
+    }
+    let skip = lines.len().saturating_sub(usize::from(inner.height));
+    f.render_widget(
+        Paragraph::new(Text::from(lines.split_off(skip))).style(theme().popup()),
+        inner,
+    );
+}
+
+pub fn render_settings(
+    f: &mut Frame,
+    settings: &Settings,
+    selected: usize,
+    editing: Option<&str>,
+    status: Option<&Message>,
+) {
+    let area = centered_rect(70, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let mut lines = vec![];
+    for (i, (key, description, _)) in SETTINGS.iter().enumerate() {
+        let focused = i == selected;
+        let value = match editing {
+            Some(value) if focused => format!("{value}█").fg(theme().accent),
+            _ => settings.get(key).fg(theme().text),
+        };
+        let marker = if focused { "▶ " } else { "  " };
+        lines.push(Line::from(vec![
+            format!("{marker}{key:<18}").fg(theme().highlight),
+            value,
+        ]));
+        lines.push(Line::from(
+            format!("    {description}").fg(theme().muted).italic(),
+        ));
+    }
+    lines.push(Line::raw(""));
+    if let Some(status) = status {
+        lines.push(Line::styled(status.text().to_string(), status.style()));
+        lines.push(Line::raw(""));
+    }
+    lines.push(if editing.is_some() {
+        Line::from(vec![
+            "Enter".fg(theme().success).bold(),
+            " save  ".into(),
+            "Esc".fg(theme().highlight),
+            " cancel".into(),
+        ])
+    } else {
+        Line::from(vec![
+            "j/k".fg(theme().highlight),
+            " move  ".into(),
+            "Enter".fg(theme().success).bold(),
+            " edit  ".into(),
+            "Esc".fg(theme().highlight),
+            " close".into(),
+        ])
+    });
+
+    f.render_widget(
+        Paragraph::new(Text::from(lines))
+            .block(
+                Block::default()
+                    .title(" Settings ")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme().accent)),
             )
-            .style(Style::default().bg(Color::Black)),
+            .style(theme().popup()),
         area,
     );
 }
@@ -207,7 +531,7 @@ pub fn render_peer_config(f: &mut Frame, config: &str, suggested_path: &str) {
     f.render_widget(Clear, area);
 
     let mut lines: Vec<Line> = vec![
-        Line::from("New Peer Config".fg(Color::Cyan).bold()),
+        Line::from("New Peer Config".fg(theme().accent).bold()),
         Line::raw(""),
     ];
     lines.extend(config.lines().map(Line::raw));
@@ -215,11 +539,11 @@ pub fn render_peer_config(f: &mut Frame, config: &str, suggested_path: &str) {
     lines.push(Line::from(format!("Suggested file: {suggested_path}")));
     lines.push(Line::raw(""));
     lines.push(Line::from(vec![
-        "s".fg(Color::Green).bold(),
+        "s".fg(theme().success).bold(),
         " save  ".into(),
-        "q".fg(Color::Yellow).bold(),
+        "q".fg(theme().highlight).bold(),
         " qr  ".into(),
-        "Esc".fg(Color::DarkGray),
+        "Esc".fg(theme().muted),
         " close".into(),
     ]));
 
@@ -229,9 +553,9 @@ pub fn render_peer_config(f: &mut Frame, config: &str, suggested_path: &str) {
                 Block::default()
                     .title(" Peer ")
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan)),
+                    .border_style(Style::default().fg(theme().accent)),
             )
-            .style(Style::default().bg(Color::Black))
+            .style(theme().popup())
             .wrap(Wrap { trim: false }),
         area,
     );
@@ -263,9 +587,9 @@ pub fn render_peer_qr(f: &mut Frame, qr: &QrCode) {
     let mut lines = qr_lines;
     lines.push(Line::raw(""));
     lines.push(Line::from(vec![
-        "b".fg(Color::Yellow).bold(),
+        "b".fg(theme().highlight).bold(),
         " back  ".into(),
-        "Esc".fg(Color::DarkGray),
+        "Esc".fg(theme().muted),
         " close".into(),
     ]));
 
@@ -275,45 +599,37 @@ pub fn render_peer_qr(f: &mut Frame, qr: &QrCode) {
                 Block::default()
                     .title(" Peer Config QR ")
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan)),
+                    .border_style(Style::default().fg(theme().accent)),
             )
-            .style(Style::default().bg(Color::Black))
+            .style(theme().popup())
             .alignment(Alignment::Center),
         area,
     );
 }
 
-pub fn render_help(f: &mut Frame) {
+/// Renders the key table generated from the active keymap.
+pub fn render_help(f: &mut Frame, keys: &[(String, &str)]) {
     let area = centered_rect(50, 60, f.area());
     f.render_widget(Clear, area);
 
-    let keys = [
-        ("j / ↓", "Move down"),
-        ("k / ↑", "Move up"),
-        ("g / G", "First / Last"),
-        ("Enter", "Edit tunnel config"),
-        ("d", "Toggle details"),
-        ("a", "Add tunnel"),
-        ("e", "Export all tunnels to zip"),
-        ("x", "Delete tunnel"),
-        ("p", "Add peer (server only)"),
-        ("t", "Toggle tunnel"),
-        ("r", "Refresh"),
-        ("?", "Help"),
-        ("q", "Quit"),
-    ];
-
+    let width = keys
+        .iter()
+        .map(|(k, _)| k.chars().count())
+        .max()
+        .unwrap_or(0);
     let mut lines: Vec<Line> = vec![
-        Line::from("Keyboard Shortcuts".fg(Color::Cyan).bold()),
+        Line::from("Keyboard Shortcuts".fg(theme().accent).bold()),
         Line::raw(""),
     ];
-    lines.extend(
-        keys.iter()
-            .map(|(k, d)| Line::from(vec![format!("  {k:<10}").fg(Color::Yellow), (*d).into()])),
-    );
+    lines.extend(keys.iter().map(|(k, d)| {
+        Line::from(vec![
+            format!("  {k:<width$}  ").fg(theme().highlight),
+            (*d).into(),
+        ])
+    }));
     lines.push(Line::raw(""));
     lines.push(Line::from(
-        "Press any key to close".fg(Color::DarkGray).italic(),
+        "Press any key to close".fg(theme().muted).italic(),
     ));
 
     f.render_widget(
@@ -322,9 +638,9 @@ pub fn render_help(f: &mut Frame) {
                 Block::default()
                     .title(" Help ")
                     .borders(Borders::ALL)
-                    .border_style(Style::default().fg(Color::Cyan)),
+                    .border_style(Style::default().fg(theme().accent)),
             )
-            .style(Style::default().bg(Color::Black)),
+            .style(theme().popup()),
         area,
     );
 }
@@ -357,87 +673,305 @@ pub fn format_bytes(b: u64) -> String {
     format_size(b, BINARY)
 }
 
+/// Splits the edit form popup into the field list, the status line and the
+/// help lines.
+fn edit_form_layout(area: Rect) -> (Rect, std::rc::Rc<[Rect]>) {
+    let area = centered_rect(80, 85, area);
+    let inner = Block::default().borders(Borders::ALL).inner(area);
+    let chunks = Layout::vertical([
+        Constraint::Min(0),
+        Constraint::Length(1),
+        Constraint::Length(2),
+    ])
+    .split(inner);
+    (area, chunks)
+}
+
+/// Returns the edit form field drawn at `column`/`row`, mirroring the line
+/// layout of [`render_edit_form`].
+pub fn edit_form_field_at(
+    screen: Rect,
+    state: &EditFormState,
+    column: u16,
+    row: u16,
+) -> Option<usize> {
+    let (_, chunks) = edit_form_layout(screen);
+    if !chunks[0].contains((column, row).into()) {
+        return None;
+    }
+    // One section header, the interface fields, the kill switch, a blank line
+    // and the peer header precede the peer fields.
+    let line = usize::from(row - chunks[0].y).checked_sub(1)?;
+    let peer_line = line.checked_sub(PEER_FIELD_START + 2);
+    match peer_line {
+        Some(i) if i < EDIT_PEER_KEYS.len() && !state.peers.is_empty() => {
+            Some(PEER_FIELD_START + i)
+        }
+        _ if line <= KILL_SWITCH_FIELD => Some(line),
+        _ => None,
+    }
+}
+
 pub fn render_edit_form(f: &mut Frame, state: &EditFormState) {
-    let area = centered_rect(80, 70, f.area());
+    let (area, chunks) = edit_form_layout(f.area());
     f.render_widget(Clear, area);
 
-    let labels = [
-        "Address:",
-        "DNS:",
-        "ListenPort:",
-        "MTU:",
-        "Endpoint:",
-        "AllowedIPs:",
-        "Keepalive:",
-    ];
-
     let block = Block::default()
         .title(format!(" Edit: {} ", state.tunnel_name))
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(theme().accent));
+    f.render_widget(block, area);
+
+    let mut lines = vec![section("Interface")];
+    for (i, (key, input)) in EDIT_INTERFACE_KEYS.iter().zip(&state.interface).enumerate() {
+        let invalid = state.invalid_field == Some(i);
+        lines.push(edit_field_line(
+            key,
+            input,
+            i == state.focused_field,
+            invalid,
+        ));
+    }
 
+    let (mark, color) = if state.kill_switch {
+        ("[x] on", theme().success)
+    } else {
+        ("[ ] off", theme().muted)
+    };
+    let color = if state.focused_field == KILL_SWITCH_FIELD {
+        theme().accent
+    } else {
+        color
+    };
+    let mut kill_switch = vec![
+        format!("  {:<22}", "Kill switch:").fg(theme().highlight),
+        mark.fg(color),
+    ];
+    if state.kill_switch {
+        kill_switch.push(
+            "  endpoint host names are resolved on save; save again if their IPs change"
+                .fg(theme().muted),
+        );
+    }
+    lines.push(Line::from(kill_switch));
+    lines.push(Line::default());
+
+    match state.peers.get(state.peer_index) {
+        Some(peer) => {
+            lines.push(section(&format!(
+                "Peer {}/{}",
+                state.peer_index + 1,
+                state.peers.len()
+            )));
+            for (i, (key, input)) in EDIT_PEER_KEYS.iter().zip(&peer.inputs).enumerate() {
+                let focused = state.focused_field == PEER_FIELD_START + i;
+                let invalid = state.invalid_field == Some(PEER_FIELD_START + i);
+                lines.push(edit_field_line(key, input, focused, invalid));
+            }
+        }
+        None => {
+            lines.push(section("Peers"));
+            lines.push(Line::from(
+                "  No peers. Press Ctrl+N to add one.".fg(theme().muted),
+            ));
+        }
+    }
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+
+    let help = vec![
+        Line::from(vec![
+            "[Tab/↑↓]".fg(theme().highlight),
+            " navigate  ".into(),
+            "[PgUp/PgDn]".fg(theme().highlight),
+            " peer  ".into(),
+            "[^N]".fg(theme().highlight),
+            " add peer  ".into(),
+            "[^D]".fg(theme().highlight),
+            " remove peer  ".into(),
+            "[Enter]".fg(theme().success),
+            " save  ".into(),
+            "[Esc]".fg(theme().highlight),
+            " cancel".into(),
+        ]),
+        Line::from(vec![
+            "[Space]".fg(theme().highlight),
+            " kill switch  ".into(),
+            "[^X]".fg(theme().highlight),
+            " split tunnel  ".into(),
+            "[^T]".fg(theme().highlight),
+            " toggle".into(),
+        ]),
+    ];
+    f.render_widget(Paragraph::new(help).alignment(Alignment::Center), chunks[2]);
+}
+
+/// Renders one form row. The focused value shows a cursor and scrolls to
+/// keep it visible, since hook commands are often wider than the popup.
+fn edit_field_line<'a>(key: &str, input: &'a Input, focused: bool, invalid: bool) -> Line<'a> {
+    let label_color = if invalid {
+        theme().error
+    } else {
+        theme().highlight
+    };
+    let label = format!("  {:<22}", format!("{key}:")).fg(label_color);
+    if !focused {
+        let color = if invalid { theme().error } else { theme().text };
+        return Line::from(vec![label, format!("[{}]", input.value()).fg(color)]);
+    }
+    let scroll = input.visual_scroll(EDIT_VALUE_WIDTH);
+    let chars: Vec<char> = input.value().chars().skip(scroll).collect();
+    let cursor = input
+        .visual_cursor()
+        .saturating_sub(scroll)
+        .min(chars.len());
+    let before: String = chars[..cursor].iter().collect();
+    let after: String = chars[cursor..].iter().collect();
+    Line::from(vec![
+        label,
+        format!("[{before}█{after}]").fg(theme().accent),
+    ])
+}
+
+pub fn render_raw_editor(f: &mut Frame, state: &mut RawEditorState) {
+    let area = f.area();
+    f.render_widget(Clear, area);
+
+    let modified = if state.is_modified() {
+        " [modified]"
+    } else {
+        ""
+    };
+    let block = Block::default()
+        .title(format!(" Raw config: {}{modified} ", state.tunnel_name))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme().accent));
     let inner = block.inner(area);
     f.render_widget(block, area);
 
     let chunks = Layout::vertical([
-        Constraint::Length(1),
         Constraint::Min(0),
         Constraint::Length(1),
         Constraint::Length(1),
     ])
     .split(inner);
+    let text_area = chunks[0];
+    let height = usize::from(text_area.height).max(1);
+    let width = usize::from(text_area.width.saturating_sub(GUTTER_WIDTH)).max(1);
+
+    let (row, col) = state.area.cursor();
+    let (top, left) = &mut state.scroll;
+    *top = (*top).min(row).max((row + 1).saturating_sub(height));
+    *left = (*left).min(col).max((col + 1).saturating_sub(width));
+
+    let lines: Vec<Line> = state
+        .area
+        .lines()
+        .iter()
+        .enumerate()
+        .skip(*top)
+        .take(height)
+        .map(|(n, line)| {
+            let mut spans = vec![format!("{:>4} ", n + 1).fg(theme().muted)];
+            spans.extend(scroll_spans(
+                config_line_spans(line, state.reveal_keys),
+                *left,
+            ));
+            Line::from(spans)
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), text_area);
+    f.set_cursor_position((
+        text_area.x + GUTTER_WIDTH + (col - *left) as u16,
+        text_area.y + (row - *top) as u16,
+    ));
 
-    let fields_area = chunks[1];
-    let help_area = chunks[3];
-
-    let field_rows = Layout::vertical([
-        Constraint::Length(1),
-        Constraint::Length(1),
-        Constraint::Length(1),
-        Constraint::Length(1),
-        Constraint::Length(1),
-        Constraint::Length(1),
-        Constraint::Length(1),
-    ])
-    .split(fields_area);
-
-    for (i, (label, row_area)) in labels.iter().zip(field_rows.iter()).enumerate() {
-        let row_chunks =
-            Layout::horizontal([Constraint::Length(14), Constraint::Min(0)]).split(*row_area);
-
-        let label_widget =
-            Paragraph::new(format!("  {}", label)).style(Style::default().fg(Color::Yellow));
-        f.render_widget(label_widget, row_chunks[0]);
-
-        let is_focused = i == state.focused_field;
-        let value = state.inputs[i].value();
-        let display = if is_focused {
-            let cursor_pos = state.inputs[i].visual_cursor();
-            let (before, after) = value.split_at(cursor_pos);
-            format!("[{}█{}]", before, after)
-        } else {
-            format!("[{}]", value)
-        };
+    let status = match &state.status {
+        Some(msg) => Line::styled(format!(" {}", msg.text()), msg.style()),
+        None => {
+            let lints = lint_config(&WgConfig::parse(&state.area.text()));
+            match lints.iter().max_by_key(|l| l.severity) {
+                Some(worst) => {
+                    let mut line = lint_line(worst);
+                    if lints.len() > 1 {
+                        line.push_span(format!(" (+{} more)", lints.len() - 1).fg(theme().muted));
+                    }
+                    line
+                }
+                None => Line::from(" ✓ No problems found".fg(theme().success)),
+            }
+        }
+    };
+    f.render_widget(Paragraph::new(status), chunks[1]);
+
+    let reveal = if state.reveal_keys {
+        " hide keys  "
+    } else {
+        " reveal keys  "
+    };
+    let help = Line::from(vec![
+        "[^S]".fg(theme().success),
+        " save  ".into(),
+        "[Esc]".fg(theme().highlight),
+        " close  ".into(),
+        "[^R]".fg(theme().highlight),
+        reveal.into(),
+        "[^E]".fg(theme().highlight),
+        " open in $EDITOR".into(),
+    ]);
+    f.render_widget(Paragraph::new(help).alignment(Alignment::Center), chunks[2]);
+}
 
-        let style = if is_focused {
-            Style::default().fg(Color::Cyan)
+/// Highlights one line of a config: section headers, keys, values and
+/// comments. Secret values are replaced by bullets of the same length so
+/// the cursor still lines up.
+fn config_line_spans(line: &str, reveal_keys: bool) -> Vec<Span<'static>> {
+    if line.trim_start().starts_with(';') {
+        return vec![line.to_string().fg(theme().muted)];
+    }
+    let (body, comment) = line.split_at(line.find('#').unwrap_or(line.len()));
+    let trimmed = body.trim();
+
+    let mut spans = if trimmed.starts_with('[') && trimmed.ends_with(']') {
+        vec![body.to_string().fg(theme().accent).bold()]
+    } else if let Some((key, value)) = body.split_once('=') {
+        let secret = SECRET_KEYS
+            .iter()
+            .any(|k| k.eq_ignore_ascii_case(key.trim()));
+        let value = if secret && !reveal_keys {
+            value
+                .chars()
+                .map(|c| if c.is_whitespace() { c } else { '•' })
+                .collect()
         } else {
-            Style::default().fg(Color::White)
+            value.to_string()
         };
-
-        let input_widget = Paragraph::new(display).style(style);
-        f.render_widget(input_widget, row_chunks[1]);
+        vec![
+            key.to_string().fg(theme().highlight),
+            "=".fg(theme().muted),
+            value.fg(theme().text),
+        ]
+    } else {
+        vec![body.to_string().fg(theme().text)]
+    };
+    if !comment.is_empty() {
+        spans.push(comment.to_string().fg(theme().muted));
     }
+    spans
+}
 
-    let help = Line::from(vec![
-        "[Tab/↑↓]".fg(Color::Yellow),
-        " navigate  ".into(),
-        "[Enter]".fg(Color::Green),
-        " save  ".into(),
-        "[Esc]".fg(Color::Yellow),
-        " cancel  ".into(),
-        "[t]".fg(Color::Yellow),
-        " toggle".into(),
-    ]);
-    f.render_widget(Paragraph::new(help).alignment(Alignment::Center), help_area);
+/// Drops the first `skip` characters of a line made of `spans`.
+fn scroll_spans(spans: Vec<Span<'static>>, mut skip: usize) -> Vec<Span<'static>> {
+    spans
+        .into_iter()
+        .filter_map(|span| {
+            let len = span.content.chars().count();
+            if skip >= len {
+                skip -= len;
+                return None;
+            }
+            let content: String = span.content.chars().skip(skip).collect();
+            skip = 0;
+            Some(Span::styled(content, span.style))
+        })
+        .collect()
 }
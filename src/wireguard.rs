@@ -3,30 +3,39 @@ use std::{
     fs,
     io::Write,
     net::{IpAddr, Ipv4Addr},
-    path::{Path, PathBuf},
+    path::PathBuf,
     process::{Command, Output},
+    time::Duration,
 };
 
 use zip::{write::SimpleFileOptions, ZipWriter};
 
 use crate::{
+    cidr::Cidr,
+    config::{Section, SectionKind, WgConfig, peer_names, set_tags, tags},
     error::Error,
+    killswitch::{has_kill_switch, is_kill_switch_line, set_kill_switch},
+    lint::{Severity, is_valid_key, lint_config, lint_listen_port},
+    privileged,
+    settings::settings,
     types::{
-        EditTunnelDraft, InterfaceInfo, NewServerDraft, NewTunnelDraft, PeerConfig, PeerInfo,
-        Tunnel,
+        CloneTunnelDraft, EDIT_INTERFACE_KEYS, EDIT_PEER_KEYS, EditTunnelDraft, InterfaceInfo,
+        NewServerDraft, NewTunnelDraft, PeerConfig, PeerDraft, PeerInfo, RouteConflict,
+        RouteSource, Tunnel,
     },
 };
 
-const CONFIG_DIR: &str = "/etc/wireguard";
-
-const CMD_WG: &str = "wg";
-const CMD_WG_QUICK: &str = "wg-quick";
-const CMD_IP: &str = "ip";
+pub(crate) const CMD_WG: &str = "wg";
+pub(crate) const CMD_WG_QUICK: &str = "wg-quick";
+pub(crate) const CMD_IP: &str = "ip";
 const CMD_CURL: &str = "curl";
 const CMD_WGET: &str = "wget";
 const ENDPOINT_PLACEHOLDER: &str = "__ENDPOINT__";
 const DNS_BLOCK_PLACEHOLDER: &str = "__DNS_BLOCK__";
 
+/// Keys whose repeated lines form a comma-separated list.
+const LIST_KEYS: &[&str] = &["Address", "DNS", "AllowedIPs"];
+
 const KIB: u64 = 1024;
 const MIB: u64 = KIB * 1024;
 const GIB: u64 = MIB * 1024;
@@ -40,28 +49,35 @@ pub fn check_dependencies() -> Vec<&'static str> {
         .collect()
 }
 
+/// Messages `wg`, `wg-quick` and `ip` print when not run as root.
+const PERMISSION_ERRORS: &[&str] = &[
+    "Operation not permitted",
+    "Permission denied",
+    "must be run as root",
+];
+
 fn command_exists(cmd: &str) -> bool {
     which::which(cmd).is_ok()
 }
 
-fn wg_error(output: &Output, default: &str) -> Error {
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    let msg = stderr.trim();
-    Error::WgTui(if msg.is_empty() {
-        default.into()
-    } else {
-        msg.to_string()
-    })
+pub(crate) fn wg_error(output: &Output, cmd: &str) -> Error {
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if PERMISSION_ERRORS.iter().any(|e| stderr.contains(e)) {
+        return Error::PermissionDenied(format!("{cmd}: {stderr}"));
+    }
+    Error::CommandFailed {
+        cmd: cmd.into(),
+        status: output.status.code(),
+        stderr,
+    }
 }
 
-fn validate_interface_name(name: &str) -> Result<(), Error> {
+pub(crate) fn validate_interface_name(name: &str) -> Result<(), Error> {
     if name.is_empty() {
-        return Err(Error::WgTui("Interface name is required".into()));
+        return Err(Error::invalid("Name", "is required"));
     }
     if name.chars().any(|c| c.is_whitespace() || c == '/') {
-        return Err(Error::WgTui(
-            "Interface name cannot contain spaces or '/'".into(),
-        ));
+        return Err(Error::invalid("Name", "cannot contain spaces or '/'"));
     }
     Ok(())
 }
@@ -69,12 +85,12 @@ fn validate_interface_name(name: &str) -> Result<(), Error> {
 pub fn detect_public_ip() -> Option<String> {
     let output = if command_exists(CMD_CURL) {
         Command::new(CMD_CURL)
-            .args(["-fsSL", "https://api.ipify.org"])
+            .args(["-fsSL", "--max-time", "10", "https://api.ipify.org"])
             .output()
             .ok()?
     } else if command_exists(CMD_WGET) {
         Command::new(CMD_WGET)
-            .args(["-qO-", "https://api.ipify.org"])
+            .args(["-qO-", "-T", "10", "https://api.ipify.org"])
             .output()
             .ok()?
     } else {
@@ -92,32 +108,44 @@ pub fn detect_public_ip() -> Option<String> {
     }
 }
 
+/// Directory holding the tunnel configs, from the `config_dir` setting.
+pub(crate) fn config_dir() -> PathBuf {
+    settings().config_dir
+}
+
+/// The `PersistentKeepalive` line for new client peers, empty when disabled.
+fn keepalive_line() -> String {
+    match settings().keepalive {
+        0 => String::new(),
+        seconds => format!("PersistentKeepalive = {seconds}\n"),
+    }
+}
+
 pub fn discover_tunnels() -> Vec<Tunnel> {
-    let Ok(entries) = fs::read_dir(Path::new(CONFIG_DIR)) else {
-        return vec![];
-    };
+    try_discover_tunnels().unwrap_or_default()
+}
 
-    let mut tunnels: Vec<_> = entries
-        .flatten()
-        .filter_map(|e| {
-            let path = e.path();
-            (path.extension()? == "conf").then_some(())?;
+fn try_discover_tunnels() -> Result<Vec<Tunnel>, Error> {
+    Ok(privileged::list_configs()?
+        .into_iter()
+        .filter_map(|path| {
             Some(Tunnel {
-                name: path.file_stem()?.to_string_lossy().into(),
+                name: path
+                    .file_name()?
+                    .to_str()?
+                    .strip_suffix(".conf")?
+                    .to_string(),
                 config_path: path,
                 ..Default::default()
             })
         })
-        .collect();
-
-    tunnels.sort_by(|a, b| a.name.cmp(&b.name));
-    tunnels
+        .collect())
 }
 
 pub fn generate_private_key() -> Result<String, Error> {
     let output = Command::new(CMD_WG).arg("genkey").output()?;
     if !output.status.success() {
-        return Err(wg_error(&output, "wg genkey failed"));
+        return Err(wg_error(&output, "wg genkey"));
     }
     let key = String::from_utf8_lossy(&output.stdout).trim().to_string();
     if key.is_empty() {
@@ -137,7 +165,7 @@ pub fn derive_public_key(private_key: &str) -> Result<String, Error> {
     }
     let output = child.wait_with_output()?;
     if !output.status.success() {
-        return Err(wg_error(&output, "wg pubkey failed"));
+        return Err(wg_error(&output, "wg pubkey"));
     }
     let key = String::from_utf8_lossy(&output.stdout).trim().to_string();
     if key.is_empty() {
@@ -205,7 +233,7 @@ pub fn suggest_server_address() -> String {
 fn used_interface_ipv4_addresses() -> HashSet<Ipv4Addr> {
     let mut used = HashSet::new();
     for tunnel in discover_tunnels() {
-        if let Ok(content) = fs::read_to_string(&tunnel.config_path) {
+        if let Ok(content) = read_tunnel_config(&tunnel.name) {
             for addr in parse_interface_addresses(&content) {
                 if let Some(ip) = parse_ipv4_address(&addr) {
                     used.insert(ip);
@@ -217,60 +245,17 @@ fn used_interface_ipv4_addresses() -> HashSet<Ipv4Addr> {
 }
 
 fn parse_interface_addresses(content: &str) -> Vec<String> {
-    let mut addrs = Vec::new();
-    let mut in_interface = false;
-
-    for line in content.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
-            continue;
-        }
-        if line.starts_with('[') && line.ends_with(']') {
-            in_interface = line.eq_ignore_ascii_case("[Interface]");
-            continue;
-        }
-        if !in_interface {
-            continue;
-        }
-        let Some((key, value)) = line.split_once('=') else {
-            continue;
-        };
-        if key.trim().eq_ignore_ascii_case("Address") {
-            addrs.extend(
-                value
-                    .split(',')
-                    .map(str::trim)
-                    .filter(|v| !v.is_empty())
-                    .map(str::to_string),
-            );
-        }
-    }
-
-    addrs
+    WgConfig::parse(content)
+        .interface()
+        .map(|i| i.get_list("Address"))
+        .unwrap_or_default()
 }
 
 fn parse_interface_value(content: &str, key: &str) -> Option<String> {
-    let mut in_interface = false;
-    for line in content.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
-            continue;
-        }
-        if line.starts_with('[') && line.ends_with(']') {
-            in_interface = line.eq_ignore_ascii_case("[Interface]");
-            continue;
-        }
-        if !in_interface {
-            continue;
-        }
-        let Some((k, v)) = line.split_once('=') else {
-            continue;
-        };
-        if k.trim().eq_ignore_ascii_case(key) {
-            return Some(v.trim().to_string());
-        }
-    }
-    None
+    WgConfig::parse(content)
+        .interface()?
+        .get(key)
+        .map(str::to_string)
 }
 
 fn parse_ipv4_address(value: &str) -> Option<Ipv4Addr> {
@@ -289,62 +274,156 @@ pub fn is_interface_active(name: &str) -> bool {
 }
 
 pub fn get_interface_info(name: &str) -> Option<InterfaceInfo> {
-    let output = Command::new(CMD_WG).arg("show").arg(name).output().ok()?;
-
-    output
-        .status
-        .success()
-        .then(|| parse_wg_output(&String::from_utf8_lossy(&output.stdout)))
+    privileged::wg_show(name)
+        .ok()
+        .map(|output| parse_wg_output(&output))
 }
 
 pub fn wg_quick(action: &str, name: &str) -> Result<(), Error> {
-    let output = Command::new(CMD_WG_QUICK).arg(action).arg(name).output()?;
+    privileged::wg_quick(action, name)
+}
 
-    if !output.status.success() {
-        return Err(wg_error(&output, &format!("wg-quick {action} failed")));
+fn sync_interface_with_content(name: &str, content: &str) -> Result<(), Error> {
+    privileged::wg_syncconf(name, &WgConfig::parse(content).strip())
+}
+
+pub fn delete_tunnel(name: &str, is_active: bool) -> Result<(), Error> {
+    if is_active {
+        wg_quick("down", name)?;
+    }
+    privileged::remove(&format!("{name}.conf"))
+}
+
+/// Renames the config of `old` to `new`. An active tunnel is brought down
+/// under its old name and up again under the new one; if that fails, the
+/// old config is restored and brought up again.
+pub fn rename_tunnel(old: &str, new: &str, is_active: bool) -> Result<(), Error> {
+    let new = new.trim();
+    validate_interface_name(new)?;
+    if new == old {
+        return Err(Error::invalid("Name", "is the same as the old one"));
+    }
+    let (from, to) = (format!("{old}.conf"), format!("{new}.conf"));
+    if privileged::exists(&to) {
+        return Err(Error::AlreadyExists(format!("Tunnel '{new}'")));
+    }
+
+    if is_active {
+        wg_quick("down", old)?;
+    }
+    let result = move_config(old, new).and_then(|()| {
+        if is_active {
+            wg_quick("up", new)
+        } else {
+            Ok(())
+        }
+    });
+    let Err(e) = result else {
+        return Ok(());
+    };
+
+    // Put the config back under its old name and restart it there.
+    let rollback = (|| {
+        if !privileged::exists(&from) && privileged::exists(&to) {
+            move_config(new, old)?;
+        }
+        if is_active {
+            wg_quick("up", old)?;
+        }
+        Ok::<_, Error>(())
+    })();
+    match rollback {
+        Ok(()) => Err(e),
+        Err(rollback) => Err(Error::WgTui(format!(
+            "{e}; restoring '{old}' also failed: {rollback}"
+        ))),
     }
+}
 
+/// Moves the config of `old`, and its backup if any, to `new`.
+fn move_config(old: &str, new: &str) -> Result<(), Error> {
+    privileged::rename(&format!("{old}.conf"), &format!("{new}.conf"))?;
+    if privileged::exists(&backup_file(old)) {
+        privileged::rename(&backup_file(old), &backup_file(new))?;
+    }
     Ok(())
 }
 
-fn sync_interface_with_content(name: &str, content: &str) -> Result<(), Error> {
-    let temp_dir = std::env::temp_dir();
-    let temp_conf = temp_dir.join(format!("wg-tui-{name}.conf"));
-    let temp_stripped = temp_dir.join(format!("wg-tui-{name}.stripped"));
+/// Stores comma-separated `tags` in the config of `name`. Only the leading
+/// comment changes, so this skips linting and backups.
+pub fn set_tunnel_tags(name: &str, tags: &str) -> Result<(), Error> {
+    let tags: Vec<String> = tags
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect();
+    let content = read_tunnel_config(name)?;
+    privileged::write(&format!("{name}.conf"), &set_tags(&content, &tags))
+}
 
-    fs::write(&temp_conf, content)?;
+/// Copies the config of `draft.source` to a new tunnel, optionally with a
+/// fresh private key and a different ListenPort. Returns the public key of
+/// the copy when a new key was generated, since its peers need it.
+pub fn clone_tunnel(draft: &CloneTunnelDraft) -> Result<Option<String>, Error> {
+    let name = draft.name.trim();
+    validate_interface_name(name)?;
+    let file = format!("{name}.conf");
+    if privileged::exists(&file) {
+        return Err(Error::AlreadyExists(format!("Tunnel '{name}'")));
+    }
 
-    let strip_output = Command::new(CMD_WG_QUICK)
-        .arg("strip")
-        .arg(&temp_conf)
-        .output()?;
-    if !strip_output.status.success() {
-        return Err(wg_error(&strip_output, "wg-quick strip failed"));
+    let mut content = read_tunnel_config(&draft.source)?;
+    let mut public_key = None;
+    if parse_yes_no(&draft.fresh_keys)? {
+        let (private, public) = generate_keypair()?;
+        content = set_interface_value(&content, "PrivateKey", &private);
+        public_key = Some(public);
     }
+    let listen_port = draft.listen_port.trim();
+    if !listen_port.is_empty() && listen_port.parse::<u16>().is_err() {
+        return Err(Error::invalid("ListenPort", "must be a valid number"));
+    }
+    content = set_interface_value(&content, "ListenPort", listen_port);
 
-    fs::write(&temp_stripped, &strip_output.stdout)?;
+    check_config_lints(name, &content)?;
+    privileged::write(&file, &content)?;
+    Ok(public_key)
+}
 
-    let sync_output = Command::new(CMD_WG)
-        .arg("syncconf")
-        .arg(name)
-        .arg(&temp_stripped)
-        .output()?;
-    if !sync_output.status.success() {
-        return Err(wg_error(&sync_output, "wg syncconf failed"));
-    }
+pub fn has_listen_port(name: &str) -> bool {
+    read_tunnel_config(name).is_ok_and(|c| parse_interface_value(&c, "ListenPort").is_some())
+}
 
-    let _ = fs::remove_file(temp_conf);
-    let _ = fs::remove_file(temp_stripped);
-    Ok(())
+/// Suggests a ListenPort for a new server: one above the highest port used by
+/// any existing tunnel, or the `listen_port` setting.
+pub fn suggest_listen_port() -> u16 {
+    discover_tunnels()
+        .iter()
+        .filter_map(|t| read_tunnel_config(&t.name).ok())
+        .filter_map(|c| parse_interface_value(&c, "ListenPort")?.parse::<u16>().ok())
+        .max()
+        .map_or(settings().listen_port, |p| p.saturating_add(1))
 }
 
-pub fn delete_tunnel(name: &str, is_active: bool) -> Result<(), Error> {
-    if is_active {
-        wg_quick("down", name)?;
+/// Sets `key` in the `[Interface]` section of `content`, or removes it when
+/// `value` is empty.
+fn set_interface_value(content: &str, key: &str, value: &str) -> String {
+    let config = WgConfig::parse(content);
+    let current = config
+        .interface()
+        .map(|i| section_value(i, key))
+        .unwrap_or_default();
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    if let Some(&(_, start, end)) = section_spans(&lines)
+        .iter()
+        .find(|s| s.0 == SectionKind::Interface)
+    {
+        edit_section(&mut lines, start, end, &[key], &[current], &[value.into()]);
     }
-    let path = Path::new(CONFIG_DIR).join(format!("{name}.conf"));
-    fs::remove_file(path)?;
-    Ok(())
+    let mut result = lines.join("\n");
+    result.push('\n');
+    result
 }
 
 pub fn expand_path(path: &str) -> PathBuf {
@@ -356,12 +435,12 @@ pub fn import_tunnel(source_path: &str) -> Result<String, Error> {
     let source = expand_path(source_path);
 
     if !source.exists() {
-        return Err(Error::WgTui("Source file does not exist".into()));
+        return Err(Error::NotFound(format!("{}", source.display())));
     }
 
     let extension = source.extension().and_then(|e| e.to_str());
     if extension != Some("conf") {
-        return Err(Error::WgTui("File must have .conf extension".into()));
+        return Err(Error::invalid("Path", "must have a .conf extension"));
     }
 
     let name = source
@@ -372,19 +451,27 @@ pub fn import_tunnel(source_path: &str) -> Result<String, Error> {
         ))?
         .to_string();
 
-    let dest = Path::new(CONFIG_DIR).join(format!("{name}.conf"));
-    if dest.exists() {
-        return Err(Error::WgTui(format!("Tunnel '{name}' already exists")));
+    let dest = format!("{name}.conf");
+    if privileged::exists(&dest) {
+        return Err(Error::AlreadyExists(format!("Tunnel '{name}'")));
     }
 
-    fs::copy(&source, &dest)?;
+    privileged::write(&dest, &fs::read_to_string(&source)?)?;
     Ok(name)
 }
 
-pub fn export_tunnels_to_zip(dest_path: &str) -> Result<PathBuf, Error> {
+/// Writes the configs of `names`, or of every tunnel if `None`, to a zip
+/// archive. Returns its path and the number of configs written.
+pub fn export_tunnels_to_zip(
+    dest_path: &str,
+    names: Option<&[String]>,
+) -> Result<(PathBuf, usize), Error> {
     let dest = expand_path(dest_path);
 
-    let tunnels = discover_tunnels();
+    let mut tunnels = discover_tunnels();
+    if let Some(names) = names {
+        tunnels.retain(|t| names.contains(&t.name));
+    }
     if tunnels.is_empty() {
         return Err(Error::WgTui("No tunnels to export".into()));
     }
@@ -394,7 +481,7 @@ pub fn export_tunnels_to_zip(dest_path: &str) -> Result<PathBuf, Error> {
     let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
 
     for tunnel in &tunnels {
-        let content = fs::read_to_string(&tunnel.config_path)?;
+        let content = read_tunnel_config(&tunnel.name)?;
         let filename = format!("{}.conf", tunnel.name);
         zip.start_file(&filename, options)?;
         zip.write_all(content.as_bytes())?;
@@ -402,31 +489,125 @@ pub fn export_tunnels_to_zip(dest_path: &str) -> Result<PathBuf, Error> {
 
     zip.finish()?;
 
-    Ok(dest)
+    Ok((dest, tunnels.len()))
 }
 
 pub fn is_full_tunnel_config(name: &str) -> bool {
-    let path = Path::new(CONFIG_DIR).join(format!("{name}.conf"));
-    let Ok(content) = fs::read_to_string(path) else {
-        return false;
+    tunnel_allowed_ips(name).iter().any(Cidr::is_default)
+}
+
+fn tunnel_allowed_ips(name: &str) -> Vec<Cidr> {
+    let Ok(content) = read_tunnel_config(name) else {
+        return vec![];
     };
+    parse_peer_allowed_ips(&content)
+        .iter()
+        .filter_map(|v| v.parse().ok())
+        .collect()
+}
+
+/// Compares the AllowedIPs of `name` with other active tunnels and the host
+/// routing table. Default routes are skipped on both sides; those are covered
+/// by the full-tunnel check.
+pub fn find_route_conflicts(name: &str) -> Vec<RouteConflict> {
+    let prefixes: Vec<_> = tunnel_allowed_ips(name)
+        .into_iter()
+        .filter(|c| !c.is_default())
+        .collect();
+    if prefixes.is_empty() {
+        return vec![];
+    }
+
+    let tunnels: Vec<_> = discover_tunnels()
+        .into_iter()
+        .filter(|t| t.name != name && is_interface_active(&t.name))
+        .map(|t| t.name)
+        .collect();
 
-    content.lines().any(|line| {
-        let line = line.trim();
-        if line.starts_with('#') || line.starts_with(';') {
-            return false;
+    let mut existing: Vec<(Cidr, RouteSource)> = Vec::new();
+    for tunnel in &tunnels {
+        for cidr in tunnel_allowed_ips(tunnel) {
+            existing.push((cidr, RouteSource::Tunnel(tunnel.clone())));
         }
-        let Some((key, value)) = line.split_once('=') else {
-            return false;
+    }
+    for family in ["-4", "-6"] {
+        let Ok(output) = Command::new(CMD_IP)
+            .args([family, "route", "show", "table", "main"])
+            .output()
+        else {
+            continue;
         };
-        if key.trim().eq_ignore_ascii_case("AllowedIPs") {
-            return value
-                .split(',')
-                .map(str::trim)
-                .any(|v| v == "0.0.0.0/0" || v == "::/0");
+        if !output.status.success() {
+            continue;
         }
-        false
-    })
+        for (cidr, dev) in parse_ip_routes(&String::from_utf8_lossy(&output.stdout)) {
+            let via_tunnel = dev
+                .as_ref()
+                .is_some_and(|d| d == name || tunnels.contains(d));
+            if !via_tunnel {
+                existing.push((cidr, RouteSource::Route { dev }));
+            }
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    for prefix in &prefixes {
+        for (cidr, source) in &existing {
+            if !cidr.is_default() && prefix.overlaps(cidr) {
+                conflicts.push(RouteConflict {
+                    prefix: *prefix,
+                    existing: *cidr,
+                    source: source.clone(),
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+/// Parses `ip route show table main` output into destination prefixes and
+/// their device. Default, loopback, link-local and multicast routes are
+/// skipped.
+fn parse_ip_routes(output: &str) -> Vec<(Cidr, Option<String>)> {
+    /// Route types that can prefix a destination in the main table.
+    const ROUTE_TYPES: &[&str] = &[
+        "unicast",
+        "multicast",
+        "blackhole",
+        "unreachable",
+        "prohibit",
+        "throw",
+    ];
+
+    let mut routes = Vec::new();
+    for line in output.lines() {
+        let mut parts = line.split_whitespace();
+        let mut dest = parts.next();
+        if dest.is_some_and(|d| ROUTE_TYPES.contains(&d)) {
+            dest = parts.next();
+        }
+        let Some(Ok(cidr)) = dest.map(str::parse::<Cidr>) else {
+            continue;
+        };
+        if is_local_only(cidr.addr()) {
+            continue;
+        }
+        let mut dev = None;
+        while let Some(part) = parts.next() {
+            if part == "dev" {
+                dev = parts.next().map(str::to_string);
+            }
+        }
+        routes.push((cidr, dev));
+    }
+    routes
+}
+
+fn is_local_only(addr: IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(a) => a.is_loopback() || a.is_link_local() || a.is_multicast(),
+        IpAddr::V6(a) => a.is_loopback() || a.is_unicast_link_local() || a.is_multicast(),
+    }
 }
 
 pub fn create_tunnel(draft: &NewTunnelDraft) -> Result<(), Error> {
@@ -439,20 +620,20 @@ pub fn create_tunnel(draft: &NewTunnelDraft) -> Result<(), Error> {
     let allowed_ips = normalize_list(&draft.allowed_ips);
     let endpoint = draft.endpoint.trim();
 
-    if private_key.is_empty()
-        || address.is_empty()
-        || peer_public_key.is_empty()
-        || allowed_ips.is_empty()
-        || endpoint.is_empty()
-    {
-        return Err(Error::WgTui("Missing required fields".into()));
+    let required = [
+        ("PrivateKey", private_key),
+        ("Address", address),
+        ("PublicKey", peer_public_key),
+        ("AllowedIPs", allowed_ips.as_str()),
+        ("Endpoint", endpoint),
+    ];
+    if let Some((field, _)) = required.iter().find(|(_, v)| v.is_empty()) {
+        return Err(Error::invalid(*field, "is required"));
     }
 
-    fs::create_dir_all(CONFIG_DIR)?;
-
-    let path = Path::new(CONFIG_DIR).join(format!("{name}.conf"));
-    if path.exists() {
-        return Err(Error::WgTui(format!("Tunnel '{name}' already exists")));
+    let file = format!("{name}.conf");
+    if privileged::exists(&file) {
+        return Err(Error::AlreadyExists(format!("Tunnel '{name}'")));
     }
 
     let dns = normalize_list(&draft.dns);
@@ -469,9 +650,21 @@ pub fn create_tunnel(draft: &NewTunnelDraft) -> Result<(), Error> {
     content.push_str(&format!("PublicKey = {peer_public_key}\n"));
     content.push_str(&format!("AllowedIPs = {allowed_ips}\n"));
     content.push_str(&format!("Endpoint = {endpoint}\n"));
+    content.push_str(&keepalive_line());
 
-    fs::write(path, content)?;
-    Ok(())
+    if parse_yes_no(&draft.kill_switch)? {
+        content = set_kill_switch(&content, &[endpoint])?;
+    }
+
+    privileged::write(&file, &content)
+}
+
+fn parse_yes_no(value: &str) -> Result<bool, Error> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "y" | "yes" => Ok(true),
+        "" | "n" | "no" => Ok(false),
+        _ => Err(Error::invalid("Answer", "must be y or n")),
+    }
 }
 
 pub fn create_server_tunnel(draft: &NewServerDraft) -> Result<(), Error> {
@@ -483,23 +676,23 @@ pub fn create_server_tunnel(draft: &NewServerDraft) -> Result<(), Error> {
     let listen_port = draft.listen_port.trim();
     let egress_interface = draft.egress_interface.trim();
 
-    if private_key.is_empty()
-        || address.is_empty()
-        || listen_port.is_empty()
-        || egress_interface.is_empty()
-    {
-        return Err(Error::WgTui("Missing required fields".into()));
+    let required = [
+        ("PrivateKey", private_key),
+        ("Address", address),
+        ("ListenPort", listen_port),
+        ("Egress interface", egress_interface),
+    ];
+    if let Some((field, _)) = required.iter().find(|(_, v)| v.is_empty()) {
+        return Err(Error::invalid(*field, "is required"));
     }
 
     let listen_port: u16 = listen_port
         .parse()
-        .map_err(|_| Error::WgTui("Listen port must be a valid number".into()))?;
+        .map_err(|_| Error::invalid("ListenPort", "must be a valid number"))?;
 
-    fs::create_dir_all(CONFIG_DIR)?;
-
-    let path = Path::new(CONFIG_DIR).join(format!("{name}.conf"));
-    if path.exists() {
-        return Err(Error::WgTui(format!("Tunnel '{name}' already exists")));
+    let file = format!("{name}.conf");
+    if privileged::exists(&file) {
+        return Err(Error::AlreadyExists(format!("Tunnel '{name}'")));
     }
 
     let post_up = format!(
@@ -518,8 +711,7 @@ pub fn create_server_tunnel(draft: &NewServerDraft) -> Result<(), Error> {
     content.push_str(&format!("ListenPort = {listen_port}\n"));
     content.push_str(&format!("PrivateKey = {private_key}\n"));
 
-    fs::write(path, content)?;
-    Ok(())
+    privileged::write(&file, &content)
 }
 
 fn parse_wg_output(output: &str) -> InterfaceInfo {
@@ -546,7 +738,10 @@ fn parse_wg_output(output: &str) -> InterfaceInfo {
             }
             ("endpoint", Some(p)) => p.endpoint = Some(val.into()),
             ("allowed ips", Some(p)) => p.allowed_ips = val.split(", ").map(Into::into).collect(),
-            ("latest handshake", Some(p)) => p.latest_handshake = Some(val.into()),
+            ("latest handshake", Some(p)) => {
+                p.latest_handshake = Some(val.into());
+                p.handshake_age = parse_handshake_age(val);
+            }
             ("transfer", Some(p)) => {
                 let parts: Vec<_> = val.split(", ").collect();
                 if let Some(rx) = parts.first() {
@@ -565,6 +760,30 @@ fn parse_wg_output(output: &str) -> InterfaceInfo {
     info
 }
 
+/// Parses the age `wg show` prints for the latest handshake, such as
+/// `1 hour, 2 minutes, 3 seconds ago` or `Now`.
+fn parse_handshake_age(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("now") {
+        return Some(Duration::ZERO);
+    }
+    let mut secs = 0;
+    for part in value.strip_suffix("ago")?.split(',') {
+        let mut words = part.split_whitespace();
+        let count: u64 = words.next()?.parse().ok()?;
+        let unit = match words.next()?.trim_end_matches('s') {
+            "year" => 365 * 24 * 3600,
+            "day" => 24 * 3600,
+            "hour" => 3600,
+            "minute" => 60,
+            "second" => 1,
+            _ => return None,
+        };
+        secs += count * unit;
+    }
+    Some(Duration::from_secs(secs))
+}
+
 fn normalize_list(value: &str) -> String {
     value
         .split(',')
@@ -590,64 +809,20 @@ fn parse_bytes(s: &str) -> u64 {
 }
 
 fn parse_peer_allowed_ips(content: &str) -> Vec<String> {
-    let mut in_peer = false;
-    let mut allowed = Vec::new();
-
-    for line in content.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
-            continue;
-        }
-        if line.starts_with('[') && line.ends_with(']') {
-            in_peer = line.eq_ignore_ascii_case("[Peer]");
-            continue;
-        }
-        if !in_peer {
-            continue;
-        }
-        let Some((key, value)) = line.split_once('=') else {
-            continue;
-        };
-        if key.trim().eq_ignore_ascii_case("AllowedIPs") {
-            allowed.extend(
-                value
-                    .split(',')
-                    .map(str::trim)
-                    .filter(|v| !v.is_empty())
-                    .map(str::to_string),
-            );
-        }
-    }
-
-    allowed
+    WgConfig::parse(content)
+        .peers()
+        .flat_map(|p| p.get_list("AllowedIPs"))
+        .collect()
 }
 
 fn is_server_config(content: &str) -> bool {
-    let mut in_interface = false;
-    for line in content.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
-            continue;
-        }
-        if line.starts_with('[') && line.ends_with(']') {
-            in_interface = line.eq_ignore_ascii_case("[Interface]");
-            continue;
-        }
-        if !in_interface {
-            continue;
-        }
-        let Some((key, _)) = line.split_once('=') else {
-            continue;
-        };
-        let key = key.trim();
-        if key.eq_ignore_ascii_case("PostUp")
-            || key.eq_ignore_ascii_case("PostDown")
-            || key.eq_ignore_ascii_case("SaveConfig")
-        {
-            return true;
-        }
-    }
-    false
+    WgConfig::parse(content).interface().is_some_and(|i| {
+        i.get("SaveConfig").is_some()
+            || ["PostUp", "PostDown"].iter().any(|key| {
+                i.get_all(key)
+                    .any(|v| !is_kill_switch_line(&format!("{key} = {v}")))
+            })
+    })
 }
 
 fn next_peer_ipv4(base: Ipv4Addr, used: &HashSet<Ipv4Addr>) -> Option<Ipv4Addr> {
@@ -666,155 +841,406 @@ fn next_peer_ipv4(base: Ipv4Addr, used: &HashSet<Ipv4Addr>) -> Option<Ipv4Addr>
 }
 
 pub fn parse_tunnel_config(name: &str) -> Result<EditTunnelDraft, Error> {
-    let path = Path::new(CONFIG_DIR).join(format!("{name}.conf"));
-    let content = fs::read_to_string(&path)
-        .map_err(|_| Error::WgTui(format!("Could not read config for tunnel '{name}'")))?;
-
-    let mut address = String::new();
-    let mut dns = String::new();
-    let mut listen_port = String::new();
-    let mut mtu = String::new();
-    let mut peer_endpoint = String::new();
-    let mut peer_allowed_ips = String::new();
-    let mut peer_persistent_keepalive = String::new();
-
-    let mut current_section = "";
-    let mut peer_count = 0;
-
-    for line in content.lines() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
-            continue;
-        }
+    let content = read_tunnel_config(name)?;
+    Ok(draft_from_content(name, &content))
+}
 
-        if line.starts_with('[') && line.ends_with(']') {
-            if line.eq_ignore_ascii_case("[Peer]") {
-                peer_count += 1;
-            }
-            current_section = line;
-            continue;
-        }
+fn draft_from_content(name: &str, content: &str) -> EditTunnelDraft {
+    let config = WgConfig::parse(content);
+    let interface = EDIT_INTERFACE_KEYS
+        .iter()
+        .map(|key| {
+            config
+                .interface()
+                .map(|i| section_value(i, key))
+                .unwrap_or_default()
+        })
+        .collect();
+    let peers = config
+        .peers()
+        .enumerate()
+        .map(|(i, peer)| PeerDraft {
+            values: EDIT_PEER_KEYS
+                .iter()
+                .map(|key| section_value(peer, key))
+                .collect(),
+            source: Some(i),
+        })
+        .collect();
 
-        let Some((key, value)) = line.split_once('=') else {
-            continue;
-        };
-        let key = key.trim();
-        let value = value.trim();
+    EditTunnelDraft {
+        name: name.to_string(),
+        interface,
+        peers,
+        kill_switch: has_kill_switch(&config),
+    }
+}
 
-        if current_section.eq_ignore_ascii_case("[Interface]") {
-            if key.eq_ignore_ascii_case("Address") {
-                address = value.to_string();
-            } else if key.eq_ignore_ascii_case("DNS") {
-                dns = value.to_string();
-            } else if key.eq_ignore_ascii_case("ListenPort") {
-                listen_port = value.to_string();
-            } else if key.eq_ignore_ascii_case("MTU") {
-                mtu = value.to_string();
-            }
-        } else if current_section.eq_ignore_ascii_case("[Peer]") && peer_count == 1 {
-            if key.eq_ignore_ascii_case("Endpoint") {
-                peer_endpoint = value.to_string();
-            } else if key.eq_ignore_ascii_case("AllowedIPs") {
-                peer_allowed_ips = value.to_string();
-            } else if key.eq_ignore_ascii_case("PersistentKeepalive") {
-                peer_persistent_keepalive = value.to_string();
-            }
+/// Joins repeated keys into one value: lists with `, `, hook commands with
+/// `; `. Kill switch hooks are managed separately and left out.
+fn section_value(section: &Section, key: &str) -> String {
+    let separator = if LIST_KEYS.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+        ", "
+    } else {
+        "; "
+    };
+    section
+        .get_all(key)
+        .filter(|v| !is_kill_switch_line(&format!("{key} = {v}")))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+pub fn read_tunnel_config(name: &str) -> Result<String, Error> {
+    privileged::read(&format!("{name}.conf"))
+}
+
+/// Validates `content` and replaces the config of `name` with it, keeping the
+/// previous file as `<name>.conf.bak`.
+pub fn save_tunnel_config(name: &str, content: &str) -> Result<(), Error> {
+    check_config_lints(name, content)?;
+    let file = format!("{name}.conf");
+    privileged::copy(&file, &backup_file(name))?;
+    privileged::write(&file, content)
+}
+
+/// Restarts an active tunnel with its saved config. If it does not come back
+/// up, the backup written by [`save_tunnel_config`] is restored and started.
+pub fn reload_tunnel(name: &str) -> Result<(), Error> {
+    wg_quick("down", name)?;
+    let Err(e) = wg_quick("up", name) else {
+        return Ok(());
+    };
+    privileged::copy(&backup_file(name), &format!("{name}.conf"))?;
+    wg_quick("up", name)?;
+    Err(Error::WgTui(format!("{e} (previous config restored)")))
+}
+
+fn backup_file(name: &str) -> String {
+    format!("{name}.conf.bak")
+}
+
+/// Checks the values of the edit form that would make `wg-quick` fail, naming
+/// the offending field.
+fn validate_draft(draft: &EditTunnelDraft) -> Result<(), Error> {
+    for (key, value) in EDIT_INTERFACE_KEYS.iter().zip(&draft.interface) {
+        let value = value.trim();
+        if matches!(*key, "ListenPort" | "MTU")
+            && !value.is_empty()
+            && value.parse::<u16>().is_err()
+        {
+            return Err(Error::invalid(*key, "must be a number up to 65535"));
         }
     }
-
-    Ok(EditTunnelDraft {
-        name: name.to_string(),
-        address,
-        dns,
-        listen_port,
-        mtu,
-        peer_endpoint,
-        peer_allowed_ips,
-        peer_persistent_keepalive,
-    })
+    for (i, peer) in draft.peers.iter().enumerate() {
+        let field = |key: &str| format!("Peer {} {key}", i + 1);
+        if !is_valid_key(peer.value("PublicKey").trim()) {
+            return Err(Error::invalid(field("PublicKey"), "not a valid key"));
+        }
+        let psk = peer.value("PresharedKey").trim();
+        if !psk.is_empty() && !is_valid_key(psk) {
+            return Err(Error::invalid(field("PresharedKey"), "not a valid key"));
+        }
+        let keepalive = peer.value("PersistentKeepalive").trim();
+        if !matches!(keepalive, "" | "off") && keepalive.parse::<u16>().is_err() {
+            return Err(Error::invalid(
+                field("PersistentKeepalive"),
+                "must be a number of seconds or off",
+            ));
+        }
+    }
+    Ok(())
 }
 
-pub fn update_tunnel_config(name: &str, draft: &EditTunnelDraft) -> Result<(), Error> {
-    let path = Path::new(CONFIG_DIR).join(format!("{name}.conf"));
-    let content = fs::read_to_string(&path)
-        .map_err(|_| Error::WgTui(format!("Could not read config for tunnel '{name}'")))?;
+pub fn update_tunnel_config(draft: &EditTunnelDraft) -> Result<(), Error> {
+    validate_draft(draft)?;
+    let name = &draft.name;
+    let content = read_tunnel_config(name)?;
 
-    let mut modified_lines = Vec::new();
-    let mut current_section = "";
-    let mut peer_count = 0;
+    let result = apply_draft(&content, draft);
 
-    for line in content.lines() {
-        let trimmed = line.trim();
+    // Every peer's endpoint stays reachable, not just the first one's.
+    let endpoints: Vec<&str> = draft
+        .peers
+        .iter()
+        .map(|p| p.value("Endpoint").trim())
+        .filter(|e| !e.is_empty())
+        .collect();
+    if draft.kill_switch && endpoints.is_empty() {
+        return Err(Error::invalid(
+            "Peer 1 Endpoint",
+            "is required for the kill switch",
+        ));
+    }
+    let endpoints = if draft.kill_switch {
+        &endpoints[..]
+    } else {
+        &[]
+    };
+    let result = set_kill_switch(&result, endpoints)?;
 
-        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
-            modified_lines.push(line.to_string());
-            continue;
+    save_tunnel_config(name, &result)
+}
+
+/// Rewrites `content` with the values of `draft`, touching only the lines of
+/// keys that changed. Comments and keys the form does not show are kept.
+fn apply_draft(content: &str, draft: &EditTunnelDraft) -> String {
+    let current = draft_from_content(&draft.name, content);
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+
+    // Edit sections bottom-up so line numbers of earlier sections stay valid.
+    let spans = section_spans(&lines);
+    let mut peer_index = spans.iter().filter(|s| s.0 == SectionKind::Peer).count();
+    let mut interface_seen = spans
+        .iter()
+        .filter(|s| s.0 == SectionKind::Interface)
+        .count();
+    for &(kind, start, end) in spans.iter().rev() {
+        match kind {
+            SectionKind::Interface => {
+                interface_seen -= 1;
+                if interface_seen == 0 {
+                    edit_section(
+                        &mut lines,
+                        start,
+                        end,
+                        EDIT_INTERFACE_KEYS,
+                        &current.interface,
+                        &draft.interface,
+                    );
+                }
+            }
+            SectionKind::Peer => {
+                peer_index -= 1;
+                let old = &current.peers[peer_index];
+                match draft.peers.iter().find(|p| p.source == Some(peer_index)) {
+                    Some(new) => {
+                        edit_section(
+                            &mut lines,
+                            start,
+                            end,
+                            EDIT_PEER_KEYS,
+                            &old.values,
+                            &new.values,
+                        );
+                    }
+                    None => {
+                        // Comments right above the next header belong to it.
+                        let mut cut = end;
+                        while cut > start + 1 && line_key(&lines[cut - 1]).is_none() {
+                            cut -= 1;
+                        }
+                        lines.drain(start..cut);
+                    }
+                }
+            }
+            SectionKind::Other => {}
         }
+    }
 
-        if trimmed.starts_with('[') && trimmed.ends_with(']') {
-            if trimmed.eq_ignore_ascii_case("[Peer]") {
-                peer_count += 1;
+    for peer in draft.peers.iter().filter(|p| p.source.is_none()) {
+        while lines.last().is_some_and(|l| l.trim().is_empty()) {
+            lines.pop();
+        }
+        lines.push(String::new());
+        lines.push("[Peer]".into());
+        for (key, value) in EDIT_PEER_KEYS.iter().zip(&peer.values) {
+            let value = value.trim();
+            if !value.is_empty() {
+                lines.push(format!("{key} = {value}"));
             }
-            current_section = trimmed;
-            modified_lines.push(line.to_string());
-            continue;
         }
+    }
+
+    let mut result = lines.join("\n");
+    if !result.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Returns `(kind, header line, end)` for every section, where `end` is the
+/// line of the next section header or the end of the file.
+fn section_spans(lines: &[String]) -> Vec<(SectionKind, usize, usize)> {
+    let headers: Vec<_> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let line = line
+                .split_once('#')
+                .map_or(line.as_str(), |(l, _)| l)
+                .trim();
+            (line.starts_with('[') && line.ends_with(']')).then(|| {
+                let kind = if line.eq_ignore_ascii_case("[Interface]") {
+                    SectionKind::Interface
+                } else if line.eq_ignore_ascii_case("[Peer]") {
+                    SectionKind::Peer
+                } else {
+                    SectionKind::Other
+                };
+                (kind, i)
+            })
+        })
+        .collect();
+
+    headers
+        .iter()
+        .enumerate()
+        .map(|(n, &(kind, start))| {
+            let end = headers.get(n + 1).map_or(lines.len(), |h| h.1);
+            (kind, start, end)
+        })
+        .collect()
+}
 
-        let Some((key_part, _)) = line.split_once('=') else {
-            modified_lines.push(line.to_string());
+/// Applies changed values to the section in `lines[start..end]`. Existing
+/// keys are replaced in place (keeping inline comments), repeated keys are
+/// collapsed, empty values remove the key and new keys are appended after
+/// the section's last entry.
+fn edit_section(
+    lines: &mut Vec<String>,
+    start: usize,
+    mut end: usize,
+    keys: &[&str],
+    old: &[String],
+    new: &[String],
+) {
+    for (i, key) in keys.iter().enumerate() {
+        let value = new[i].trim();
+        if old[i].trim() == value {
             continue;
-        };
+        }
 
-        let key = key_part.trim();
-        let new_value = get_draft_value_for_field(draft, current_section, key, peer_count);
+        let positions: Vec<_> = (start + 1..end)
+            .filter(|&n| line_key(&lines[n]).is_some_and(|k| k.eq_ignore_ascii_case(key)))
+            .filter(|&n| !is_kill_switch_line(lines[n].trim()))
+            .collect();
 
-        if let Some(new_val) = new_value {
-            if !new_val.is_empty() {
-                let modified = replace_field_value(line, key_part, new_val);
-                modified_lines.push(modified);
-            } else {
-                modified_lines.push(line.to_string());
+        match positions.split_first() {
+            Some((&first, rest)) => {
+                for &n in rest.iter().rev() {
+                    lines.remove(n);
+                    end -= 1;
+                }
+                if value.is_empty() {
+                    lines.remove(first);
+                    end -= 1;
+                } else {
+                    let key_part = lines[first].split_once('=').map_or("", |(k, _)| k);
+                    lines[first] = replace_field_value(&lines[first], key_part, value);
+                }
             }
-        } else {
-            modified_lines.push(line.to_string());
+            None if !value.is_empty() => {
+                let at = (start + 1..end)
+                    .rev()
+                    .find(|&n| line_key(&lines[n]).is_some())
+                    .map_or(start + 1, |n| n + 1);
+                lines.insert(at, format!("{key} = {value}"));
+                end += 1;
+            }
+            None => {}
         }
     }
+}
 
-    let mut result = modified_lines.join("\n");
-    if content.ends_with('\n') && !result.ends_with('\n') {
-        result.push('\n');
+fn line_key(line: &str) -> Option<&str> {
+    let trimmed = line.trim();
+    if trimmed.starts_with('#') || trimmed.starts_with(';') {
+        return None;
     }
+    trimmed.split_once('=').map(|(k, _)| k.trim())
+}
 
-    fs::write(&path, result)?;
-    Ok(())
+/// Lints `content` as the new config for `name` and refuses it if any
+/// error-level problem is found.
+fn check_config_lints(name: &str, content: &str) -> Result<(), Error> {
+    let config = WgConfig::parse(content);
+    let others: Vec<_> = discover_tunnels()
+        .into_iter()
+        .filter(|t| t.name != name)
+        .filter_map(|t| {
+            let config = WgConfig::parse(&read_tunnel_config(&t.name).ok()?);
+            Some((t.name, config))
+        })
+        .collect();
+
+    let mut lints = lint_config(&config);
+    lints.extend(lint_listen_port(
+        &config,
+        others.iter().map(|(n, c)| (n.as_str(), c)),
+    ));
+
+    let errors: Vec<_> = lints
+        .iter()
+        .filter(|l| l.severity == Severity::Error)
+        .map(|l| l.message.as_str())
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::WgTui(format!(
+            "Config not saved: {}",
+            errors.join("; ")
+        )))
+    }
 }
 
-fn get_draft_value_for_field<'a>(
-    draft: &'a EditTunnelDraft,
-    section: &str,
-    key: &str,
-    peer_count: usize,
-) -> Option<&'a str> {
-    if section.eq_ignore_ascii_case("[Interface]") {
-        if key.eq_ignore_ascii_case("Address") {
-            return Some(&draft.address);
-        } else if key.eq_ignore_ascii_case("DNS") {
-            return Some(&draft.dns);
-        } else if key.eq_ignore_ascii_case("ListenPort") {
-            return Some(&draft.listen_port);
-        } else if key.eq_ignore_ascii_case("MTU") {
-            return Some(&draft.mtu);
-        }
-    } else if section.eq_ignore_ascii_case("[Peer]") && peer_count == 1 {
-        if key.eq_ignore_ascii_case("Endpoint") {
-            return Some(&draft.peer_endpoint);
-        } else if key.eq_ignore_ascii_case("AllowedIPs") {
-            return Some(&draft.peer_allowed_ips);
-        } else if key.eq_ignore_ascii_case("PersistentKeepalive") {
-            return Some(&draft.peer_persistent_keepalive);
+/// Discovers all tunnels with their live interface state and parsed configs.
+/// Runs `wg` and `ip` for every tunnel, so it may take a moment.
+pub fn load_tunnels() -> Vec<Tunnel> {
+    try_load_tunnels().unwrap_or_default()
+}
+
+/// Like [`load_tunnels`], but fails if the config directory cannot be listed
+/// instead of returning no tunnels.
+pub fn try_load_tunnels() -> Result<Vec<Tunnel>, Error> {
+    let mut tunnels = try_discover_tunnels()?;
+    for t in &mut tunnels {
+        t.is_active = is_interface_active(&t.name);
+        if t.is_active {
+            t.interface = get_interface_info(&t.name);
         }
     }
-    None
+    load_tunnel_configs(&mut tunnels);
+    Ok(tunnels)
+}
+
+/// Reads every tunnel's config and attaches lint warnings, including
+/// ListenPort collisions between tunnels, and kill switch state.
+pub fn load_tunnel_configs(tunnels: &mut [Tunnel]) {
+    let names: Vec<_> = tunnels.iter().map(|t| t.name.clone()).collect();
+    let contents: Vec<_> = tunnels
+        .iter()
+        .map(|t| read_tunnel_config(&t.name).ok())
+        .collect();
+    let configs: Vec<_> = contents
+        .iter()
+        .map(|c| c.as_deref().map(WgConfig::parse))
+        .collect();
+
+    for (i, tunnel) in tunnels.iter_mut().enumerate() {
+        let (Some(content), Some(config)) = (&contents[i], &configs[i]) else {
+            continue;
+        };
+        tunnel.is_server = config
+            .interface()
+            .is_some_and(|i| i.get("ListenPort").is_some());
+        tunnel.endpoints = config
+            .peers()
+            .filter_map(|p| p.get("Endpoint"))
+            .map(str::to_string)
+            .collect();
+        tunnel.peer_names = peer_names(content);
+        tunnel.tags = tags(content);
+        let others = configs
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .filter_map(|(j, c)| Some((names[j].as_str(), c.as_ref()?)));
+        tunnel.kill_switch = has_kill_switch(config);
+        tunnel.lints = lint_config(config);
+        tunnel.lints.extend(lint_listen_port(config, others));
+    }
 }
 
 fn replace_field_value(line: &str, key_part: &str, new_value: &str) -> String {
@@ -845,9 +1271,7 @@ fn extract_value_and_comment(after_eq: &str) -> (&str, Option<&str>) {
 }
 
 pub fn add_server_peer(name: &str) -> Result<PeerConfig, Error> {
-    let path = Path::new(CONFIG_DIR).join(format!("{name}.conf"));
-    let content = fs::read_to_string(&path)
-        .map_err(|_| Error::WgTui(format!("Could not read config for tunnel '{name}'")))?;
+    let content = read_tunnel_config(name)?;
 
     if !is_server_config(&content) {
         return Err(Error::WgTui(
@@ -868,7 +1292,7 @@ pub fn add_server_peer(name: &str) -> Result<PeerConfig, Error> {
         .ok_or_else(|| Error::WgTui("Server config missing ListenPort".into()))?;
     let listen_port: u16 = listen_port
         .parse()
-        .map_err(|_| Error::WgTui("Listen port must be a valid number".into()))?;
+        .map_err(|_| Error::invalid("ListenPort", "must be a valid number"))?;
 
     let mut used = HashSet::new();
     used.insert(base_ip);
@@ -896,10 +1320,11 @@ pub fn add_server_peer(name: &str) -> Result<PeerConfig, Error> {
     if is_interface_active(name) {
         sync_interface_with_content(name, &new_content)?;
     }
-    fs::write(&path, new_content)?;
+    privileged::write(&format!("{name}.conf"), &new_content)?;
 
     let client_config = format!(
-        "[Interface]\nPrivateKey = {peer_private_key}\nAddress = {peer_address}\n{DNS_BLOCK_PLACEHOLDER}\n[Peer]\nPublicKey = {server_public_key}\nAllowedIPs = 0.0.0.0/0, ::/0\nEndpoint = {ENDPOINT_PLACEHOLDER}\n"
+        "[Interface]\nPrivateKey = {peer_private_key}\nAddress = {peer_address}\n{DNS_BLOCK_PLACEHOLDER}\n[Peer]\nPublicKey = {server_public_key}\nAllowedIPs = 0.0.0.0/0, ::/0\nEndpoint = {ENDPOINT_PLACEHOLDER}\n{}",
+        keepalive_line()
     );
 
     Ok(PeerConfig {
@@ -908,3 +1333,154 @@ pub fn add_server_peer(name: &str) -> Result<PeerConfig, Error> {
         listen_port,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER: &str = include_str!("../tests/fixtures/server.conf");
+
+    #[test]
+    fn unchanged_draft_keeps_content() {
+        let draft = draft_from_content("wg0", SERVER);
+        assert_eq!(apply_draft(SERVER, &draft), SERVER);
+    }
+
+    #[test]
+    fn edits_removes_and_adds_peers() {
+        let mut draft = draft_from_content("wg0", SERVER);
+        draft.interface[3] = "1380".into();
+        draft.peers.remove(0);
+        draft.peers[0].values[4].clear();
+        let mut added = PeerDraft::new();
+        added.values[0] = "Ibrg7KnsGI/lJwSjYE/ejm3opimRtDjmxCnez6jWyjE=".into();
+        added.values[3] = "10.0.0.9/32".into();
+        draft.peers.push(added);
+
+        let config = WgConfig::parse(&apply_draft(SERVER, &draft));
+        assert_eq!(config.interface().unwrap().get("MTU"), Some("1380"));
+        let peers: Vec<_> = config.peers().collect();
+        assert_eq!(peers.len(), 2);
+        assert_eq!(
+            peers[0].get_list("AllowedIPs"),
+            ["10.0.0.3/32", "fd00::3/128"]
+        );
+        assert_eq!(peers[0].get("PersistentKeepalive"), None);
+        assert_eq!(peers[1].get("AllowedIPs"), Some("10.0.0.9/32"));
+    }
+
+    #[test]
+    fn names_invalid_draft_fields() {
+        let mut draft = draft_from_content("wg0", SERVER);
+        assert!(validate_draft(&draft).is_ok());
+
+        draft.interface[3] = "big".into();
+        assert_eq!(validate_draft(&draft).unwrap_err().field(), Some("MTU"));
+        draft.interface[3].clear();
+
+        draft.peers[1].values[0] = "not-a-key".into();
+        let err = validate_draft(&draft).unwrap_err();
+        assert_eq!(err.field(), Some("Peer 2 PublicKey"));
+        assert_eq!(err.exit_code(), 65);
+
+        let err = validate_interface_name("").unwrap_err();
+        assert_eq!(err.to_string(), "Name: is required");
+        let err = parse_yes_no("maybe").unwrap_err();
+        assert_eq!(err.to_string(), "Answer: must be y or n");
+    }
+
+    #[test]
+    fn parses_handshake_age() {
+        assert_eq!(parse_handshake_age("Now"), Some(Duration::ZERO));
+        assert_eq!(
+            parse_handshake_age("1 hour, 2 minutes, 3 seconds ago"),
+            Some(Duration::from_secs(3723))
+        );
+        assert_eq!(
+            parse_handshake_age("1 day, 1 second ago"),
+            Some(Duration::from_secs(86401))
+        );
+        assert_eq!(parse_handshake_age("(none)"), None);
+    }
+
+    #[test]
+    fn parses_ip_routes() {
+        // `ip -4 route show table main` on a client with a split tunnel and
+        // Docker; the routes of wg-quick's full tunnels live in another table.
+        let v4 = "\
+default via 192.168.1.1 dev eth0 proto dhcp src 192.168.1.10 metric 100
+10.0.0.0/24 dev wg0 proto kernel scope link src 10.0.0.2
+169.254.0.0/16 dev eth0 scope link metric 1000
+172.17.0.0/16 dev docker0 proto kernel scope link src 172.17.0.1 linkdown
+192.168.1.0/24 dev eth0 proto kernel scope link src 192.168.1.10 metric 100
+192.168.1.1 dev eth0 proto dhcp scope link src 192.168.1.10 metric 100
+blackhole 10.9.0.0/16 proto static
+";
+        let routes: Vec<_> = parse_ip_routes(v4)
+            .into_iter()
+            .map(|(cidr, dev)| (cidr.to_string(), dev))
+            .collect();
+        let dev = |d: &str| Some(d.to_string());
+        assert_eq!(
+            routes,
+            [
+                ("10.0.0.0/24".to_string(), dev("wg0")),
+                ("172.17.0.0/16".into(), dev("docker0")),
+                ("192.168.1.0/24".into(), dev("eth0")),
+                ("192.168.1.1/32".into(), dev("eth0")),
+                ("10.9.0.0/16".into(), None),
+            ]
+        );
+
+        // `ip -6 route show table main`: loopback, link-local and `default`,
+        // which is not a prefix, are skipped.
+        let v6 = "\
+::1 dev lo proto kernel metric 256 pref medium
+2001:db8:1::/64 dev eth0 proto ra metric 100 expires 86000sec pref medium
+fd00::/64 dev wg0 proto kernel metric 256 pref medium
+fe80::/64 dev eth0 proto kernel metric 256 pref medium
+default via fe80::1 dev eth0 proto ra metric 100 expires 1700sec pref medium
+";
+        let routes: Vec<_> = parse_ip_routes(v6)
+            .into_iter()
+            .map(|(cidr, dev)| (cidr.to_string(), dev))
+            .collect();
+        assert_eq!(
+            routes,
+            [
+                ("2001:db8:1::/64".to_string(), dev("eth0")),
+                ("fd00::/64".into(), dev("wg0")),
+            ]
+        );
+    }
+
+    #[test]
+    fn kill_switch_does_not_make_a_server() {
+        let client = "[Interface]\nPrivateKey = k\n\n[Peer]\nEndpoint = 1.2.3.4:51820\n";
+        let client = set_kill_switch(client, &["1.2.3.4:51820"]).unwrap();
+        assert!(!is_server_config(&client));
+        assert!(is_server_config(&set_interface_value(
+            &client,
+            "PostUp",
+            "iptables -A FORWARD -i %i -j ACCEPT"
+        )));
+        assert!(is_server_config(SERVER));
+    }
+
+    #[test]
+    fn sets_and_removes_interface_values() {
+        let content = set_interface_value(SERVER, "ListenPort", "51821");
+        let config = WgConfig::parse(&content);
+        assert_eq!(config.interface().unwrap().get("ListenPort"), Some("51821"));
+        assert_eq!(content.lines().count(), SERVER.lines().count());
+
+        let content = set_interface_value(&content, "ListenPort", "");
+        assert_eq!(
+            WgConfig::parse(&content)
+                .interface()
+                .unwrap()
+                .get("ListenPort"),
+            None
+        );
+    }
+}